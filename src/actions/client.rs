@@ -1,150 +1,305 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use base64::{Engine, prelude::BASE64_STANDARD_NO_PAD};
 use chrono::Utc;
 use log::info;
+use serde::Serialize;
 use serde_json::{Value, json};
 
 use crate::{
+    actions::{
+        game_files, installs,
+        launcher_profiles::{self, LauncherProfiles},
+        mods::ModSelection,
+        plan::{Plan, ProfileEdit},
+        receipt::{self, Receipt},
+    },
     errors::InstallerError,
     net::{
+        GameSide,
+        loaders::LoaderType,
         manifest::{self, MinecraftVersion},
-        meta::{self, LoaderType, LoaderVersion},
+        meta::{self, IntermediaryGen, LoaderVersion},
+        resolver::MetadataResolver,
     },
+    progress::Progress,
 };
 
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// The launcher profile to create for an installation.
+#[derive(Clone, Debug, Default)]
+pub struct ProfileOptions {
+    /// Name of the profile, `Ornithe (<loader>) <version>` by default.
+    pub name: Option<String>,
+    /// Arguments for the JVM, e.g. `-Xmx4G`.
+    pub java_args: Option<String>,
+    /// A game directory of its own. Mods are installed there as well.
+    pub game_dir: Option<PathBuf>,
+    /// A PNG image to use instead of the Ornithe logo.
+    pub icon: Option<PathBuf>,
+    /// The Java executable to start the game with.
+    pub java_dir: Option<PathBuf>,
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn install(
     version: MinecraftVersion,
     loader_type: LoaderType,
     loader_version: LoaderVersion,
+    intermediary_gen: IntermediaryGen,
     location: PathBuf,
-    create_profile: bool,
+    profile: Option<ProfileOptions>,
+    offline_ready: bool,
+    mods: ModSelection,
+    resolver: &MetadataResolver,
+    progress: Progress,
+    plan: &mut Plan,
 ) -> Result<(), InstallerError> {
-    if !location.exists() {
-        std::fs::create_dir_all(&location)?;
-    }
-    let location = location.canonicalize()?;
+    let location = plan.create_dir(&location)?;
+    plan.begin(&location)?;
     info!(
         "Installing Minecraft client at {}",
         location.to_str().unwrap_or("<not representable>")
     );
 
-    info!("Fetching launch jsons..");
-    let vanilla_launch_json = manifest::fetch_launch_json(&version).await?;
+    progress.phase("Fetching launch jsons..");
+    let vanilla_launch_json = manifest::fetch_launch_json(&version, resolver).await?;
 
     let ornithe_launch_json = meta::fetch_launch_json(
-        crate::net::GameSide::Client,
+        GameSide::Client,
         &version,
         &loader_type,
         &loader_version,
+        intermediary_gen,
+        resolver,
     )
     .await?;
 
-    info!("Setting up destination..");
+    let mods = mods.resolve(&version, &loader_type).await?;
+    let icon = icon_string(profile.as_ref().and_then(|profile| profile.icon.as_deref()))?;
 
-    let vanilla_profile_name = version.id.to_string() + "-vanilla";
-    let profile_name = format!(
-        "{}-loader-{}-{}-ornithe",
-        loader_type.get_name(),
-        loader_version.version,
-        version.id
-    );
+    progress.phase("Setting up destination..");
+
+    let vanilla_profile_name = installs::vanilla_profile_name(&version.id);
+    let profile_name = installs::profile_name(&loader_type, &loader_version.version, &version.id);
 
     let versions_dir = location.join("versions");
     let vanilla_profile_dir = versions_dir.join(&vanilla_profile_name);
-    let vanilla_profile_json = vanilla_profile_dir.join(vanilla_profile_name.clone() + ".json");
     let profile_dir = versions_dir.join(&profile_name);
-    let profile_json = profile_dir.join(profile_name.clone() + ".json");
 
-    if std::fs::exists(&vanilla_profile_dir).unwrap_or_default() {
-        std::fs::remove_dir_all(&vanilla_profile_dir)?;
-    }
-    if std::fs::exists(&profile_dir).unwrap_or_default() {
-        std::fs::remove_dir_all(&profile_dir)?;
+    plan.remove(&vanilla_profile_dir)?;
+    plan.remove(&profile_dir)?;
+
+    let mut receipt = Receipt::new(
+        &version.id,
+        &loader_type,
+        &loader_version,
+        receipt::intermediary_version(&ornithe_launch_json, intermediary_gen),
+        intermediary_gen,
+    );
+    receipt.add_source(crate::config::get().endpoints.version_json(&version.id));
+    receipt.add_source(
+        meta::launch_json_url(
+            &GameSide::Client,
+            &version,
+            &loader_type,
+            &loader_version.version,
+            intermediary_gen,
+            resolver,
+        )
+        .await?,
+    );
+    for source in mods.sources(&GameSide::Client) {
+        receipt.add_source(source);
     }
 
-    info!("Creating files..");
+    progress.phase("Creating files..");
+
+    let mut written = write_profile(
+        &vanilla_profile_dir,
+        &vanilla_profile_name,
+        &vanilla_launch_json,
+        !offline_ready,
+        plan,
+    )?;
+    written.extend(write_profile(
+        &profile_dir,
+        &profile_name,
+        &ornithe_launch_json,
+        true,
+        plan,
+    )?);
 
-    create_empty_jar(&vanilla_profile_dir, &vanilla_profile_name)?;
-    create_empty_jar(&profile_dir, &profile_name)?;
+    if offline_ready {
+        progress.phase("Downloading client jar..");
+        let url = version
+            .get_jar_download_url(&GameSide::Client, resolver)
+            .await?;
+        let client_jar = vanilla_profile_dir.join(vanilla_profile_name.clone() + ".jar");
+        if let Some(target) = plan.download(&url.url, &client_jar)? {
+            progress.totals(1, Some(url.size));
+            crate::net::download_file(&url.url, &target, &url.integrity(), &progress).await?;
+        }
+        receipt.add_source(url.url);
+        written.push(client_jar);
+        // Shared by every version using them, so they are not part of the receipt
+        game_files::install(
+            &location,
+            &vanilla_launch_json,
+            &ornithe_launch_json,
+            &progress,
+            plan,
+        )
+        .await?;
+    }
 
-    std::fs::write(vanilla_profile_json, vanilla_launch_json)?;
-    std::fs::write(profile_json, ornithe_launch_json)?;
+    let game_dir = match profile
+        .as_ref()
+        .and_then(|profile| profile.game_dir.as_ref())
+    {
+        Some(game_dir) => std::path::absolute(game_dir)?,
+        None => location.clone(),
+    };
+    written.extend(
+        mods.install(&game_dir, &GameSide::Client, &progress, plan)
+            .await?,
+    );
+    if !plan.is_dry_run() {
+        // Mods of a game directory outside of the launcher's are left to the user
+        for file in written.iter().filter(|file| file.starts_with(&location)) {
+            receipt.add_staged_file(&location, file, &plan.source(file))?;
+        }
+    }
 
-    if create_profile {
-        update_profiles(location, profile_name, version, loader_type)?;
+    if let Some(profile) = &profile {
+        let (key, created) = update_profiles(
+            &location,
+            &profile_name,
+            &version,
+            &loader_type,
+            profile,
+            &icon,
+            plan,
+        )?;
+        // Profiles of the user that were only pointed at the version aren't ours to remove
+        let added_before = Receipt::find(&profile_dir)
+            .is_some_and(|previous| previous.launcher_profiles.contains(&key));
+        if created || added_before {
+            receipt.launcher_profiles.push(key);
+        }
     }
 
+    plan.write(&profile_dir.join(receipt::RECEIPT_FILE), receipt.to_vec()?)?;
+    plan.commit()?;
+
     Ok(())
 }
 
-fn create_empty_jar(dir: &PathBuf, name: &String) -> Result<(), InstallerError> {
-    std::fs::create_dir_all(dir)?;
-    std::fs::File::create(dir.join(name.clone() + ".jar"))?;
-    Ok(())
+/// Writes a profile to its directory in `versions/`, optionally next to the
+/// empty jar the launcher expects until it downloads the real one. Returns the
+/// files written.
+pub fn write_profile(
+    dir: &Path,
+    name: &str,
+    launch_json: &impl Serialize,
+    empty_jar: bool,
+    plan: &mut Plan,
+) -> Result<Vec<PathBuf>, InstallerError> {
+    plan.create_dir(dir)?;
+    let mut written = Vec::new();
+    if empty_jar {
+        let jar = dir.join(name.to_owned() + ".jar");
+        plan.write(&jar, [])?;
+        written.push(jar);
+    }
+    let json = dir.join(name.to_owned() + ".json");
+    plan.write(&json, serde_json::to_string_pretty(launch_json)?)?;
+    written.push(json);
+    Ok(written)
 }
 
+/// Adds or updates the launcher profile starting the version in every profile
+/// file of the launcher, returning its key and whether it was added to any.
 fn update_profiles(
-    game_dir: PathBuf,
-    name: String,
-    version: MinecraftVersion,
-    loader_type: LoaderType,
-) -> Result<(), InstallerError> {
-    let launcher_profiles_path = game_dir.join("launcher_profiles.json");
-
-    match std::fs::read_to_string(launcher_profiles_path.clone()) {
-        Ok(launcher_profiles) => match serde_json::from_str::<Value>(&launcher_profiles) {
-            Ok(mut json) => {
-                let raw_profiles = json.as_object_mut().unwrap().get_mut("profiles").unwrap();
-                if !raw_profiles.is_object() {
-                    return Err(InstallerError(
-                        "\"profiles\" field must be an object".to_string(),
-                    ));
-                }
-                let profiles = raw_profiles.as_object_mut().unwrap();
-
-                let new_profile_name =
-                    "Ornithe (".to_owned() + loader_type.get_localized_name() + ") " + &version.id;
-
-                if profiles.contains_key(&new_profile_name) {
-                    let raw_profile = profiles.get_mut(&new_profile_name).unwrap();
-                    if !raw_profile.is_object() {
-                        return Err(InstallerError(format!(
-                            "Cannot update profile of name {new_profile_name} because it is not an object!"
-                        )));
-                    }
-
-                    raw_profile
-                        .as_object_mut()
-                        .unwrap()
-                        .insert("lastVersionId".to_string(), Value::String(name));
-                } else {
-                    let profile = json!({
-                        "name": new_profile_name,
-                        "type":"custom",
-                        "created": Utc::now(),
-                        "lastUsed": Utc::now(),
-                        "icon": get_icon_string(),
-                        "lastVersionId": name
-                    });
-                    profiles.insert(new_profile_name, profile);
-                }
-
-                std::fs::write(&launcher_profiles_path, serde_json::to_string(&json)?)?;
-
-                Ok(())
+    game_dir: &Path,
+    name: &str,
+    version: &MinecraftVersion,
+    loader_type: &LoaderType,
+    options: &ProfileOptions,
+    icon: &str,
+    plan: &mut Plan,
+) -> Result<(String, bool), InstallerError> {
+    let key = options.name.clone().unwrap_or_else(|| {
+        "Ornithe (".to_owned() + loader_type.get_localized_name() + ") " + &version.id
+    });
+    let mut created = false;
+    for path in launcher_profiles::files_to_update(game_dir) {
+        let mut launcher_profiles = LauncherProfiles::load(&path)?;
+        let profiles = launcher_profiles.profiles();
+        let added = !profiles.contains_key(&key);
+        created |= added;
+        plan.edit_launcher_profile(ProfileEdit {
+            file: path,
+            key: key.clone(),
+            last_version_id: name.to_owned(),
+            created: added,
+        });
+        let profile = profiles.entry(key.clone()).or_insert_with(|| {
+            json!({
+                "name": key,
+                "type": "custom",
+                "created": Utc::now(),
+                "lastUsed": Utc::now(),
+                "icon": icon,
+            })
+        });
+        let Some(profile) = profile.as_object_mut() else {
+            return Err(InstallerError::Conflict(format!(
+                "Cannot update profile of name {key} because it is not an object!"
+            )));
+        };
+        profile.insert("lastVersionId".to_owned(), Value::String(name.to_owned()));
+        if options.icon.is_some() {
+            profile.insert("icon".to_owned(), Value::String(icon.to_owned()));
+        }
+        if let Some(java_args) = &options.java_args {
+            profile.insert("javaArgs".to_owned(), Value::String(java_args.clone()));
+        }
+        for (field, path) in [
+            ("gameDir", &options.game_dir),
+            ("javaDir", &options.java_dir),
+        ] {
+            if let Some(path) = path {
+                let path = std::path::absolute(path)?;
+                profile.insert(
+                    field.to_owned(),
+                    Value::String(path.to_string_lossy().into_owned()),
+                );
             }
-            Err(_) => Err(InstallerError(
-                "Failed to parse launcher_profiles.json json".to_string(),
-            )),
-        },
-        Err(_) => Err(InstallerError(
-            "Failed to read launcher_profiles.json".to_string(),
-        )),
+        }
+        launcher_profiles.save(plan)?;
     }
+    Ok((key, created))
 }
 
-fn get_icon_string() -> String {
-    let base64 = BASE64_STANDARD_NO_PAD.encode(crate::ORNITHE_ICON_BYTES);
-    "data:image/png;base64,".to_string() + &base64
+/// The icon of a launcher profile, as a data url.
+fn icon_string(icon: Option<&Path>) -> Result<String, InstallerError> {
+    let bytes = match icon {
+        Some(path) => {
+            let bytes = std::fs::read(path).map_err(|e| {
+                InstallerError::io(format!("Failed to read icon {}", path.display())).caused_by(e)
+            })?;
+            if !bytes.starts_with(PNG_SIGNATURE) {
+                return Err(InstallerError::parse(format!(
+                    "Icon {} is not a PNG image",
+                    path.display()
+                )));
+            }
+            bytes
+        }
+        None => crate::ORNITHE_ICON_BYTES.to_vec(),
+    };
+    let base64 = BASE64_STANDARD_NO_PAD.encode(bytes);
+    Ok("data:image/png;base64,".to_string() + &base64)
 }
@@ -0,0 +1,225 @@
+//! The files the official launcher downloads before it starts a version:
+//! libraries, natives, assets and logging configs. Installing them up front
+//! lets the launcher start the version without network access.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+use sha1::{Digest, Sha1};
+use tokio::task::JoinSet;
+
+use crate::{
+    errors::InstallerError,
+    model::{loader::LoaderProfile, version::VersionJson},
+    net::integrity::Integrity,
+    progress::Progress,
+};
+
+use super::{plan::Plan, server};
+
+/// Where the launcher downloads asset objects from.
+const RESOURCES_URL: &str = "https://resources.download.minecraft.net";
+
+/// An asset index, mapping the paths of assets to the objects holding them.
+#[derive(Deserialize)]
+pub struct AssetObjects {
+    /// Set for old versions reading their assets from `assets/virtual/<index>`.
+    #[serde(default, rename = "virtual")]
+    pub is_virtual: bool,
+    /// Set for versions older than 1.6, reading them from `resources/`.
+    #[serde(default)]
+    pub map_to_resources: bool,
+    pub objects: BTreeMap<String, AssetObject>,
+}
+
+#[derive(Deserialize)]
+pub struct AssetObject {
+    pub hash: String,
+    pub size: u64,
+}
+
+impl AssetObject {
+    /// Where the object is kept below the assets directory.
+    pub fn path(&self, assets_dir: &Path) -> PathBuf {
+        assets_dir
+            .join("objects")
+            .join(self.hash.get(..2).unwrap_or_default())
+            .join(&self.hash)
+    }
+}
+
+/// A file to download, and how to find its checksum.
+struct GameFile {
+    url: String,
+    path: PathBuf,
+    integrity: Option<Integrity>,
+}
+
+/// Downloads the libraries, natives and assets of a vanilla version and the
+/// libraries of its Ornithe profile into a game directory, returning the
+/// files written.
+pub async fn install(
+    game_dir: &Path,
+    vanilla: &VersionJson,
+    ornithe: &LoaderProfile,
+    progress: &Progress,
+    plan: &mut Plan,
+) -> Result<Vec<PathBuf>, InstallerError> {
+    progress.phase("Resolving game files..");
+    let libraries_dir = game_dir.join("libraries");
+    let mut files = Vec::new();
+    for library in vanilla
+        .libraries
+        .iter()
+        .filter(|library| library.is_allowed())
+    {
+        let downloads = library.downloads.as_ref();
+        match downloads.and_then(|downloads| downloads.artifact.as_ref()) {
+            Some(artifact) => files.push(GameFile {
+                url: artifact.url.clone(),
+                path: libraries_dir.join(
+                    artifact
+                        .path
+                        .clone()
+                        .unwrap_or_else(|| server::split_artifact(&library.name)),
+                ),
+                integrity: Some(artifact.integrity()),
+            }),
+            // Natives only libraries have no artifact of their own
+            None if library.natives.is_some() => {}
+            None => files.push(GameFile {
+                url: server::library_url(
+                    library
+                        .url
+                        .as_deref()
+                        .unwrap_or("https://libraries.minecraft.net/"),
+                    &library.name,
+                ),
+                path: libraries_dir.join(server::split_artifact(&library.name)),
+                integrity: None,
+            }),
+        }
+        if let Some(classifier) = library.native_classifier() {
+            let native = downloads
+                .and_then(|downloads| downloads.classifiers.as_ref())
+                .and_then(|classifiers| classifiers.get(&classifier))
+                .ok_or_else(|| {
+                    InstallerError::NotFound(format!(
+                        "Library {} has no {} natives",
+                        library.name, classifier
+                    ))
+                })?;
+            let path = match &native.path {
+                Some(path) => path.clone(),
+                None => server::split_artifact(&(library.name.clone() + ":" + &classifier)),
+            };
+            files.push(GameFile {
+                url: native.url.clone(),
+                path: libraries_dir.join(path),
+                integrity: Some(native.integrity()),
+            });
+        }
+    }
+    for library in &ornithe.libraries {
+        files.push(GameFile {
+            url: server::library_url(&library.url, &library.name),
+            path: libraries_dir.join(server::split_artifact(&library.name)),
+            integrity: None,
+        });
+    }
+    if let Some(logging) = vanilla
+        .logging
+        .as_ref()
+        .and_then(|logging| logging.client.as_ref())
+    {
+        files.push(GameFile {
+            url: logging.file.url.clone(),
+            path: game_dir
+                .join("assets")
+                .join("log_configs")
+                .join(&logging.file.id),
+            integrity: Some(Integrity {
+                sha1: logging.file.sha1.clone(),
+                size: logging.file.size,
+                ..Default::default()
+            }),
+        });
+    }
+    let mut written = Vec::new();
+    if let Some(asset_index) = &vanilla.asset_index {
+        let assets_dir = game_dir.join("assets");
+        let index = assets_dir
+            .join("indexes")
+            .join(asset_index.id.clone() + ".json");
+        let text = crate::net::fetch_text(&asset_index.url).await?;
+        if let Some(sha1) = &asset_index.sha1 {
+            let actual = format!("{:x}", Sha1::digest(text.as_bytes()));
+            if &actual != sha1 {
+                return Err(InstallerError::Integrity(format!(
+                    "Asset index {} has sha1 {}, expected {}",
+                    asset_index.id, actual, sha1
+                )));
+            }
+        }
+        // Written as fetched, the launcher checks it against the same hash
+        plan.write(&index, &text)?;
+        written.push(index);
+        let objects: AssetObjects = serde_json::from_str(&text).map_err(|e| {
+            InstallerError::parse(format!("Failed to parse asset index {}", asset_index.id))
+                .caused_by(e)
+        })?;
+        for object in objects.objects.into_values() {
+            files.push(GameFile {
+                url: format!(
+                    "{}/{}/{}",
+                    RESOURCES_URL,
+                    object.hash.get(..2).unwrap_or_default(),
+                    object.hash
+                ),
+                path: object.path(&assets_dir),
+                integrity: Some(Integrity::sha1(&object.hash).with_size(object.size)),
+            });
+        }
+    }
+    let mut seen = HashSet::new();
+    files.retain(|file| seen.insert(file.path.clone()));
+
+    progress.phase("Downloading game files..");
+    // Files of unknown size, like those only checked by a maven sha1, count as empty
+    progress.totals(
+        files.len(),
+        Some(
+            files
+                .iter()
+                .filter_map(|file| file.integrity.as_ref()?.size)
+                .sum(),
+        ),
+    );
+    let mut downloads = JoinSet::new();
+    for file in files {
+        written.push(file.path.clone());
+        if let Some(target) = plan.download(&file.url, &file.path)? {
+            let progress = progress.clone();
+            downloads.spawn(async move { download(&file, &target, &progress).await });
+        }
+    }
+    while let Some(done) = downloads.join_next().await {
+        done??;
+    }
+    Ok(written)
+}
+
+async fn download(
+    file: &GameFile,
+    target: &Path,
+    progress: &Progress,
+) -> Result<(), InstallerError> {
+    let integrity = match &file.integrity {
+        Some(integrity) => integrity.clone(),
+        None => server::fetch_sha1(&file.url).await?,
+    };
+    crate::net::download_file(&file.url, target, &integrity, progress).await
+}
@@ -0,0 +1,400 @@
+//! Detection of existing Ornithe installations.
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use log::warn;
+
+use crate::{
+    actions::receipt::Receipt,
+    errors::InstallerError,
+    model::{
+        self,
+        loader::LoaderProfile,
+        mmc::{MmcPack, MmcPatch},
+    },
+    net::{
+        GameSide,
+        loaders::LoaderType,
+        meta::{self, IntermediaryGen},
+        resolver::MetadataResolver,
+    },
+};
+
+/// Suffix of the profiles the client installation adds to `versions/`.
+pub const PROFILE_SUFFIX: &str = "-ornithe";
+/// Suffix of the vanilla profiles Ornithe profiles inherit from.
+pub const VANILLA_PROFILE_SUFFIX: &str = "-vanilla";
+/// Name prefix of generated instances, followed by the Minecraft version.
+pub const INSTANCE_PREFIX: &str = "Ornithe-";
+/// File name suffix of server launch jars, following the loader id.
+pub const LAUNCH_JAR_SUFFIX: &str = "-server-launch.jar";
+/// Components of a generated instance that always come with a patch file.
+pub const INSTANCE_PATCHES: &[&str] = &["net.minecraft", "net.fabricmc.intermediary"];
+
+/// The versions an installation was set up with.
+#[derive(Clone, Debug)]
+pub struct InstalledVersions {
+    pub minecraft: String,
+    pub loader: LoaderType,
+    pub loader_version: String,
+    pub intermediary_gen: IntermediaryGen,
+    /// The intermediary version on the class path, if it could be determined.
+    pub intermediary_version: Option<String>,
+}
+
+/// An Ornithe profile in the `versions` folder of a game directory.
+#[derive(Clone, Debug)]
+pub struct ClientProfile {
+    /// The profile id, which is also the name of its directory.
+    pub name: String,
+    pub dir: PathBuf,
+    pub versions: InstalledVersions,
+}
+
+/// A server directory set up with a launch jar.
+#[derive(Clone, Debug)]
+pub struct ServerInstall {
+    pub launch_jar: PathBuf,
+    pub versions: InstalledVersions,
+    /// Libraries on the class path of the launch jar.
+    pub libraries: Vec<PathBuf>,
+}
+
+pub fn profile_name(loader_type: &LoaderType, loader_version: &str, minecraft: &str) -> String {
+    format!(
+        "{}-loader-{}-{}{}",
+        loader_type.get_name(),
+        loader_version,
+        minecraft,
+        PROFILE_SUFFIX
+    )
+}
+
+pub fn vanilla_profile_name(minecraft: &str) -> String {
+    minecraft.to_owned() + VANILLA_PROFILE_SUFFIX
+}
+
+/// Finds the Ornithe profiles of a game directory. Profiles that cannot be
+/// read are skipped.
+pub fn find_profiles(game_dir: &Path) -> Result<Vec<ClientProfile>, InstallerError> {
+    let versions_dir = game_dir.join("versions");
+    if !versions_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut profiles = Vec::new();
+    for entry in std::fs::read_dir(&versions_dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !name.ends_with(PROFILE_SUFFIX) || !entry.file_type()?.is_dir() {
+            continue;
+        }
+        match read_profile(game_dir, &name) {
+            Ok(versions) => profiles.push(ClientProfile {
+                name,
+                dir: entry.path(),
+                versions,
+            }),
+            Err(e) => warn!("Skipping profile {}: {}", name, e.report()),
+        }
+    }
+    profiles.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(profiles)
+}
+
+/// Reads the versions from an Ornithe profile in the `versions` folder of a
+/// game directory.
+pub fn read_profile(game_dir: &Path, name: &str) -> Result<InstalledVersions, InstallerError> {
+    let profile_json = game_dir
+        .join("versions")
+        .join(name)
+        .join(name.to_owned() + ".json");
+    let profile: LoaderProfile = model::parse(
+        serde_json::from_str(&std::fs::read_to_string(&profile_json).map_err(|e| {
+            InstallerError::io(format!("Failed to read profile {}", profile_json.display()))
+                .caused_by(e)
+        })?)?,
+        "launch profile",
+    )?;
+
+    let minecraft = profile
+        .inherits_from
+        .as_deref()
+        .map(|id| {
+            id.strip_suffix(VANILLA_PROFILE_SUFFIX)
+                .unwrap_or(id)
+                .to_owned()
+        })
+        .ok_or_else(|| {
+            InstallerError::parse("Profile does not inherit from a Minecraft version")
+        })?;
+    let (loader, loader_version) = crate::config::get()
+        .loaders
+        .all()
+        .iter()
+        .find_map(|loader| {
+            let prefix = loader.get_maven_name_start().to_owned() + ":";
+            profile.libraries.iter().find_map(|library| {
+                library
+                    .name
+                    .strip_prefix(&prefix)
+                    .map(|version| (loader.clone(), version.to_owned()))
+            })
+        })
+        .ok_or_else(|| {
+            InstallerError::NotFound("Profile does not contain a supported loader".to_owned())
+        })?;
+
+    let intermediary_gen = profile
+        .intermediary_generation
+        .map(IntermediaryGen)
+        .unwrap_or_default();
+    Ok(InstalledVersions {
+        minecraft,
+        loader,
+        loader_version,
+        intermediary_gen,
+        intermediary_version: super::receipt::intermediary_version(&profile, intermediary_gen),
+    })
+}
+
+/// Finds the generated instances at a location, which is either an instance
+/// or the directory instances were generated in.
+pub fn find_instances(location: &Path) -> Result<Vec<PathBuf>, InstallerError> {
+    if location.join("mmc-pack.json").is_file() {
+        return Ok(vec![location.to_owned()]);
+    }
+    let mut instances = Vec::new();
+    if location.is_dir() {
+        for entry in std::fs::read_dir(location)? {
+            let path = entry?.path();
+            let generated = path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with(INSTANCE_PREFIX));
+            if generated && path.join("mmc-pack.json").is_file() {
+                instances.push(path);
+            }
+        }
+    }
+    instances.sort();
+    Ok(instances)
+}
+
+/// The uids of the components a generated instance added for the libraries of
+/// its loader. They are taken from the patches recorded in the receipt, or for
+/// instances without one, from the loader's launch profile.
+pub async fn library_components(
+    instance: &Path,
+    versions: &InstalledVersions,
+    resolver: &MetadataResolver,
+) -> Result<HashSet<String>, InstallerError> {
+    if let Some(receipt) = Receipt::find(instance) {
+        let patches = instance.join("patches");
+        return Ok(receipt
+            .file_paths(instance)
+            .iter()
+            .filter(|file| file.parent() == Some(&patches))
+            .filter_map(|file| file.file_name()?.to_str()?.strip_suffix(".json"))
+            .filter(|uid| !INSTANCE_PATCHES.contains(uid))
+            .map(str::to_owned)
+            .collect());
+    }
+
+    let version = resolver
+        .versions()
+        .await?
+        .versions
+        .iter()
+        .find(|version| version.id == versions.minecraft)
+        .cloned()
+        .ok_or_else(|| {
+            InstallerError::NotFound(format!("Unknown Minecraft version {}", versions.minecraft))
+        })?;
+    let version_id = version.get_id(&GameSide::Client, resolver).await?;
+    let intermediary_versions = resolver
+        .intermediary_versions(versions.intermediary_gen)
+        .await?;
+    let intermediary_version = intermediary_versions.get(&version_id).ok_or_else(|| {
+        InstallerError::NotFound("Could not find matching intermediary version".to_owned())
+    })?;
+    let loader_version = resolver
+        .loader_versions()
+        .await?
+        .get(&versions.loader)
+        .and_then(|available| {
+            available
+                .iter()
+                .find(|version| version.version == versions.loader_version)
+        })
+        .ok_or_else(|| {
+            InstallerError::NotFound(format!(
+                "Could not find {} Loader version {}",
+                versions.loader.get_localized_name(),
+                versions.loader_version
+            ))
+        })?;
+    let libraries = meta::fetch_profile_libraries(
+        intermediary_version,
+        &versions.loader,
+        loader_version,
+        versions.intermediary_gen,
+        resolver,
+    )
+    .await?;
+    libraries
+        .iter()
+        .map(|library| Ok(super::mmc_pack::library_patch(library)?.1.uid))
+        .collect()
+}
+
+pub fn read_pack(instance: &Path) -> Result<MmcPack, InstallerError> {
+    model::parse(
+        serde_json::from_str(&std::fs::read_to_string(instance.join("mmc-pack.json"))?)?,
+        "mmc-pack.json",
+    )
+}
+
+/// Reads the versions from the components of a MultiMC/Prism instance. Fails
+/// for instances whose intermediary patch doesn't use Ornithe's mappings.
+pub fn read_instance(instance: &Path) -> Result<InstalledVersions, InstallerError> {
+    let pack = read_pack(instance)?;
+    let component_version = |uid: &str| {
+        pack.components
+            .iter()
+            .find(|component| component.uid == uid)
+            .and_then(|component| component.version.clone())
+    };
+    let minecraft = component_version("net.minecraft").ok_or_else(|| {
+        InstallerError::NotFound("Instance does not contain Minecraft".to_owned())
+    })?;
+    let (loader, loader_version) = crate::config::get()
+        .loaders
+        .all()
+        .iter()
+        .find_map(|loader| {
+            component_version(&loader.get_maven_uid()).map(|version| (loader.clone(), version))
+        })
+        .ok_or_else(|| {
+            InstallerError::NotFound("Instance does not contain a supported loader".to_owned())
+        })?;
+
+    // Fabric and Quilt instances only use Ornithe if their mappings are calamus
+    let not_ornithe = || {
+        InstallerError::NotFound("Instance does not use Ornithe intermediary mappings".to_owned())
+    };
+    let intermediary_patch = instance.join("patches/net.fabricmc.intermediary.json");
+    if !intermediary_patch.is_file() {
+        return Err(not_ornithe());
+    }
+    let patch: MmcPatch = model::parse(
+        serde_json::from_str(&std::fs::read_to_string(&intermediary_patch)?)?,
+        "intermediary patch",
+    )?;
+    let calamus = IntermediaryGen::default().maven_artifact();
+    let (artifact, intermediary_version) = patch
+        .libraries
+        .iter()
+        .filter_map(|library| library.name.rsplit_once(':'))
+        .find(|(artifact, _)| artifact.starts_with(&calamus))
+        .ok_or_else(not_ornithe)?;
+    // Patches written by the installer name the generation, others only the artifact
+    let intermediary_gen = match patch.intermediary_generation {
+        Some(generation) => IntermediaryGen(generation),
+        None => artifact
+            .strip_prefix(&calamus)
+            .and_then(|suffix| suffix.strip_prefix("-gen"))
+            .and_then(|generation| generation.parse().ok())
+            .map(IntermediaryGen)
+            .unwrap_or_default(),
+    };
+    let intermediary_version = Some(intermediary_version.to_owned());
+
+    Ok(InstalledVersions {
+        minecraft,
+        loader,
+        loader_version,
+        intermediary_gen,
+        intermediary_version,
+    })
+}
+
+/// Finds the launch jars in a server directory, one per loader at most.
+/// Launch jars that cannot be read are skipped.
+pub fn find_servers(dir: &Path) -> Vec<ServerInstall> {
+    let mut servers = Vec::new();
+    for loader in crate::config::get().loaders.all() {
+        let launch_jar = dir.join(loader.get_name().to_owned() + LAUNCH_JAR_SUFFIX);
+        if !launch_jar.is_file() {
+            continue;
+        }
+        match read_server(dir, loader, &launch_jar) {
+            Ok(server) => servers.push(server),
+            Err(e) => warn!("Skipping {}: {}", launch_jar.display(), e.report()),
+        }
+    }
+    servers
+}
+
+fn read_server(
+    dir: &Path,
+    loader: &LoaderType,
+    launch_jar: &Path,
+) -> Result<ServerInstall, InstallerError> {
+    let manifest = super::server::read_jar_manifest(launch_jar)?;
+    let attribute = |name: &str| {
+        manifest.get(name).cloned().ok_or_else(|| {
+            InstallerError::NotFound(format!(
+                "Couldn't find '{}' attribute in jar manifest!",
+                name
+            ))
+        })
+    };
+    let minecraft = attribute("Minecraft-Version")?;
+    let class_path = attribute("Class-Path")?;
+    let libraries = class_path
+        .split_whitespace()
+        .map(|path| dir.join(path))
+        .collect::<Vec<_>>();
+
+    // Libraries are laid out as libraries/<group>/<artifact>/<version>/<file>
+    let library_version = |maven_name: &str| {
+        let library_path = match maven_name.split_once(':') {
+            Some((group, artifact)) => group.replace('.', "/") + "/" + artifact,
+            None => maven_name.replace('.', "/"),
+        };
+        class_path.split_whitespace().find_map(|path| {
+            path.strip_prefix("libraries/")?
+                .strip_prefix(&library_path)?
+                .strip_prefix('/')?
+                .split('/')
+                .next()
+                .map(str::to_owned)
+        })
+    };
+    let loader_version = library_version(loader.get_maven_name_start()).ok_or_else(|| {
+        InstallerError::NotFound(format!(
+            "{} does not contain {} Loader",
+            launch_jar.display(),
+            loader.get_localized_name()
+        ))
+    })?;
+    let intermediary_gen = manifest
+        .get("Intermediary-Generation")
+        .and_then(|generation| generation.parse().ok())
+        .map(IntermediaryGen)
+        .unwrap_or_default();
+
+    Ok(ServerInstall {
+        launch_jar: launch_jar.to_owned(),
+        versions: InstalledVersions {
+            minecraft,
+            loader: loader.clone(),
+            loader_version,
+            intermediary_gen,
+            intermediary_version: library_version(&intermediary_gen.maven_artifact()),
+        },
+        libraries,
+    })
+}
@@ -0,0 +1,151 @@
+//! Listing of the Ornithe installations across launchers and servers.
+
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use log::warn;
+use serde::Serialize;
+
+use crate::{
+    actions::{installs, receipt::Receipt},
+    errors::InstallerError,
+};
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum InstallKind {
+    Client,
+    Mmc,
+    Server,
+}
+
+impl InstallKind {
+    pub fn name(&self) -> &str {
+        match self {
+            InstallKind::Client => "Client",
+            InstallKind::Mmc => "MultiMC/Prism",
+            InstallKind::Server => "Server",
+        }
+    }
+}
+
+/// An installation as listed by the inventory.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Installation {
+    pub kind: InstallKind,
+    /// The profile directory, instance or launch jar.
+    pub path: PathBuf,
+    pub minecraft_version: String,
+    pub loader_type: String,
+    pub loader_version: String,
+    pub intermediary_generation: u32,
+    /// Read from the installed files, or else from the receipt of the installation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub intermediary_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub installed_at: Option<DateTime<Utc>>,
+}
+
+impl Installation {
+    fn new(
+        kind: InstallKind,
+        path: PathBuf,
+        versions: installs::InstalledVersions,
+        receipt: Option<Receipt>,
+    ) -> Self {
+        // A receipt left over from another loader version doesn't describe this one
+        let receipt = receipt.filter(|receipt| {
+            receipt.loader_type == versions.loader.get_name()
+                && receipt.loader_version == versions.loader_version
+        });
+        Installation {
+            kind,
+            path,
+            minecraft_version: versions.minecraft,
+            loader_type: versions.loader.get_name().to_owned(),
+            loader_version: versions.loader_version,
+            intermediary_generation: versions.intermediary_gen.0,
+            intermediary_version: versions.intermediary_version.or_else(|| {
+                receipt
+                    .as_ref()
+                    .and_then(|receipt| receipt.intermediary_version.clone())
+            }),
+            installed_at: receipt.map(|receipt| receipt.installed_at),
+        }
+    }
+}
+
+/// Lists the installations in the given game directories, instance folders and
+/// server directories. Locations that don't exist are skipped.
+pub fn scan(
+    game_dirs: &[PathBuf],
+    instance_dirs: &[PathBuf],
+    server_dirs: &[PathBuf],
+) -> Vec<Installation> {
+    let mut installations = Vec::new();
+    for game_dir in game_dirs {
+        match installs::find_profiles(game_dir) {
+            Ok(profiles) => installations.extend(profiles.into_iter().map(|profile| {
+                let receipt = Receipt::find(&profile.dir);
+                Installation::new(InstallKind::Client, profile.dir, profile.versions, receipt)
+            })),
+            Err(e) => skip(game_dir, e),
+        }
+    }
+    for instance_dir in instance_dirs {
+        match find_instances(instance_dir) {
+            Ok(instances) => installations.extend(instances),
+            Err(e) => skip(instance_dir, e),
+        }
+    }
+    for server_dir in server_dirs {
+        let receipt = Receipt::find(server_dir);
+        installations.extend(
+            installs::find_servers(server_dir)
+                .into_iter()
+                .map(|server| {
+                    Installation::new(
+                        InstallKind::Server,
+                        server.launch_jar,
+                        server.versions,
+                        receipt.clone(),
+                    )
+                }),
+        );
+    }
+    installations
+}
+
+/// Finds the instances with a supported loader in an instance folder, whether
+/// generated by the installer or set up in the launcher. The folder may also be
+/// a single instance.
+fn find_instances(instance_dir: &Path) -> Result<Vec<Installation>, InstallerError> {
+    let mut instances = Vec::new();
+    if !instance_dir.is_dir() {
+        return Ok(instances);
+    }
+    let mut paths = if instance_dir.join("mmc-pack.json").is_file() {
+        vec![instance_dir.to_owned()]
+    } else {
+        std::fs::read_dir(instance_dir)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()?
+    };
+    paths.sort();
+    for path in paths {
+        if !path.join("mmc-pack.json").is_file() {
+            continue;
+        }
+        // Instances of other loaders are not an error here
+        if let Ok(versions) = installs::read_instance(&path) {
+            let receipt = Receipt::find(&path);
+            instances.push(Installation::new(InstallKind::Mmc, path, versions, receipt));
+        }
+    }
+    Ok(instances)
+}
+
+fn skip(location: &Path, error: InstallerError) {
+    warn!("Skipping {}: {}", location.display(), error.report());
+}
@@ -0,0 +1,541 @@
+//! Launching a client profile without a launcher, from the files of an
+//! offline-ready installation.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use log::info;
+use md5::{Digest, Md5};
+use zip::ZipArchive;
+
+use crate::{
+    actions::{
+        game_files::AssetObjects,
+        installs::{self, ClientProfile},
+        server,
+    },
+    errors::InstallerError,
+    java,
+    model::{
+        self,
+        loader::LoaderProfile,
+        version::{Argument, ArgumentValue, VersionJson, rules_allow},
+    },
+    net::loaders::LoaderType,
+    progress::Progress,
+};
+
+#[cfg(target_os = "windows")]
+const CLASSPATH_SEPARATOR: &str = ";";
+#[cfg(not(target_os = "windows"))]
+const CLASSPATH_SEPARATOR: &str = ":";
+
+/// Jvm arguments of version jsons from before they listed them.
+const LEGACY_JVM_ARGUMENTS: [&str; 3] = [
+    "-Djava.library.path=${natives_directory}",
+    "-cp",
+    "${classpath}",
+];
+
+/// The profile to launch and how to launch it.
+#[derive(Clone, Debug, Default)]
+pub struct LaunchOptions {
+    /// The Ornithe profile to launch if the game directory contains several.
+    pub profile: Option<String>,
+    pub minecraft_version: Option<String>,
+    pub loader_type: Option<LoaderType>,
+    /// Name of the offline player.
+    pub username: String,
+    pub java: Option<PathBuf>,
+    /// Additional arguments for the JVM, e.g. `-Xmx4G`.
+    pub java_args: Option<String>,
+    /// A game directory other than the installation directory.
+    pub game_dir: Option<PathBuf>,
+    /// Prints the command instead of running it.
+    pub print_command: bool,
+}
+
+impl LaunchOptions {
+    fn selects(&self, profile: &ClientProfile) -> bool {
+        self.profile
+            .as_ref()
+            .is_none_or(|name| profile.name == *name)
+            && self
+                .minecraft_version
+                .as_ref()
+                .is_none_or(|minecraft| profile.versions.minecraft == *minecraft)
+            && self
+                .loader_type
+                .as_ref()
+                .is_none_or(|loader| profile.versions.loader == *loader)
+    }
+}
+
+/// Starts an Ornithe profile of a game directory with an offline player. The
+/// game files must have been installed along with the profile.
+pub async fn launch_client(
+    location: PathBuf,
+    options: LaunchOptions,
+    progress: &Progress,
+) -> Result<(), InstallerError> {
+    let location = location.canonicalize().map_err(|e| {
+        InstallerError::io(format!("Failed to open {}", location.display())).caused_by(e)
+    })?;
+    let profile = select_profile(&location, &options)?;
+    info!("Launching {}", profile.name);
+
+    progress.phase("Reading launch jsons..");
+    let ornithe: LoaderProfile = read_json(&profile.dir.join(profile.name.clone() + ".json"))?;
+    let vanilla_name = installs::vanilla_profile_name(&profile.versions.minecraft);
+    let vanilla_dir = location.join("versions").join(&vanilla_name);
+    let vanilla: VersionJson = read_json(&vanilla_dir.join(vanilla_name.clone() + ".json"))?;
+    let game_dir = match &options.game_dir {
+        Some(game_dir) => std::path::absolute(game_dir)?,
+        None => location.clone(),
+    };
+
+    progress.phase("Preparing game files..");
+    let libraries_dir = location.join("libraries");
+    let (classpath, natives) = libraries(&libraries_dir, &vanilla, &ornithe);
+    let client_jar = vanilla_dir.join(vanilla_name + ".jar");
+    let assets_dir = location.join("assets");
+    let asset_index = match &vanilla.asset_index {
+        Some(asset_index) => {
+            let index_file = assets_dir
+                .join("indexes")
+                .join(asset_index.id.clone() + ".json");
+            match index_file.is_file() {
+                true => Some((
+                    asset_index.id.as_str(),
+                    read_json::<AssetObjects>(&index_file)?,
+                )),
+                false => return Err(missing_files(&profile, &[index_file])),
+            }
+        }
+        None => None,
+    };
+    let mut required = classpath.clone();
+    required.extend(natives.iter().map(|(jar, _)| jar.clone()));
+    required.push(client_jar.clone());
+    if let Some((_, index)) = &asset_index {
+        let objects = index.objects.values();
+        required.extend(objects.map(|object| object.path(&assets_dir)));
+    }
+    let missing = required
+        .into_iter()
+        .filter(|file| !file.is_file())
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        return Err(missing_files(&profile, &missing));
+    }
+
+    let natives_dir = profile.dir.join("natives");
+    extract_natives(&natives, &natives_dir)?;
+    let game_assets = match &asset_index {
+        Some((id, index)) => prepare_assets(id, index, &assets_dir, &game_dir)?,
+        None => assets_dir.clone(),
+    };
+
+    let mut classpath = classpath;
+    classpath.push(client_jar);
+    let classpath = classpath
+        .iter()
+        .map(|path| path.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(CLASSPATH_SEPARATOR);
+    let username = options.username.clone();
+    let values = HashMap::from([
+        ("auth_player_name", username.clone()),
+        ("auth_uuid", offline_uuid(&username)),
+        ("auth_access_token", "0".to_owned()),
+        ("auth_session", "0".to_owned()),
+        ("auth_xuid", String::new()),
+        ("clientid", String::new()),
+        ("user_type", "legacy".to_owned()),
+        ("user_properties", "{}".to_owned()),
+        ("version_name", profile.name.clone()),
+        ("version_type", vanilla.release_type.clone()),
+        ("game_directory", game_dir.to_string_lossy().into_owned()),
+        ("assets_root", assets_dir.to_string_lossy().into_owned()),
+        ("game_assets", game_assets.to_string_lossy().into_owned()),
+        (
+            "assets_index_name",
+            vanilla
+                .assets
+                .clone()
+                .or_else(|| vanilla.asset_index.as_ref().map(|index| index.id.clone()))
+                .unwrap_or_default(),
+        ),
+        (
+            "natives_directory",
+            natives_dir.to_string_lossy().into_owned(),
+        ),
+        (
+            "library_directory",
+            libraries_dir.to_string_lossy().into_owned(),
+        ),
+        ("classpath_separator", CLASSPATH_SEPARATOR.to_owned()),
+        ("classpath", classpath),
+        ("launcher_name", "ornithe-installer".to_owned()),
+        ("launcher_version", crate::VERSION.to_owned()),
+    ]);
+
+    let mut arguments = Vec::new();
+    if let Some(java_args) = &options.java_args {
+        arguments.extend(java_args.split_whitespace().map(str::to_owned));
+    }
+    match vanilla
+        .arguments
+        .as_ref()
+        .filter(|arguments| !arguments.jvm.is_empty())
+    {
+        Some(vanilla_arguments) => arguments.extend(resolve(&vanilla_arguments.jvm)),
+        None => arguments.extend(LEGACY_JVM_ARGUMENTS.map(str::to_owned)),
+    }
+    if let Some(logging) = vanilla
+        .logging
+        .as_ref()
+        .and_then(|logging| logging.client.as_ref())
+    {
+        let config = assets_dir.join("log_configs").join(&logging.file.id);
+        arguments.push(
+            logging
+                .argument
+                .replace("${path}", &config.to_string_lossy()),
+        );
+    }
+    if let Some(ornithe_arguments) = &ornithe.arguments {
+        arguments.extend(resolve(&ornithe_arguments.jvm));
+    }
+    arguments.push(ornithe.main_class.clone());
+    match vanilla
+        .arguments
+        .as_ref()
+        .filter(|arguments| !arguments.game.is_empty())
+    {
+        Some(vanilla_arguments) => arguments.extend(resolve(&vanilla_arguments.game)),
+        None => arguments.extend(
+            vanilla
+                .minecraft_arguments
+                .iter()
+                .flat_map(|arguments| arguments.split_whitespace())
+                .map(str::to_owned),
+        ),
+    }
+    if let Some(ornithe_arguments) = &ornithe.arguments {
+        arguments.extend(resolve(&ornithe_arguments.game));
+    }
+    let arguments = arguments
+        .iter()
+        .map(|argument| substitute(argument, &values))
+        .collect::<Vec<_>>();
+
+    let java_binary = match &options.java {
+        Some(java) => java.clone(),
+        // Runtimes are never downloaded here, launching must work offline
+        None => {
+            let required = java::required_major(vanilla.java_version.as_ref());
+            let runtime = crate::config::get()
+                .runtimes_dir
+                .as_deref()
+                .and_then(|dir| java::find_managed_runtime(dir, required))
+                .or_else(|| java::find_runtime(required));
+            java::binary_or_path(runtime, required)
+        }
+    };
+
+    if options.print_command {
+        let command = std::iter::once(java_binary.to_string_lossy().into_owned())
+            .chain(arguments)
+            .map(|argument| quote(&argument))
+            .collect::<Vec<_>>()
+            .join(" ");
+        writeln!(std::io::stdout(), "{}", command)?;
+        return Ok(());
+    }
+
+    std::fs::create_dir_all(&game_dir)?;
+    let status = Command::new(&java_binary)
+        .args(arguments)
+        .current_dir(&game_dir)
+        .stdout(Stdio::inherit())
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .status()
+        .map_err(|e| {
+            InstallerError::io(format!("Failed to start {}", java_binary.display())).caused_by(e)
+        })?;
+    if !status.success() {
+        return Err(InstallerError::other(format!(
+            "Minecraft exited with {}",
+            status
+        )));
+    }
+    Ok(())
+}
+
+/// The single Ornithe profile of a game directory matching the options.
+fn select_profile(
+    location: &Path,
+    options: &LaunchOptions,
+) -> Result<ClientProfile, InstallerError> {
+    let mut profiles = installs::find_profiles(location)?
+        .into_iter()
+        .filter(|profile| options.selects(profile))
+        .collect::<Vec<_>>();
+    match profiles.len() {
+        0 => Err(InstallerError::NotFound(format!(
+            "{} contains no matching Ornithe profile",
+            location.display()
+        ))),
+        1 => Ok(profiles.remove(0)),
+        _ => Err(InstallerError::Conflict(format!(
+            "{} contains several Ornithe profiles, select one of: {}",
+            location.display(),
+            profiles
+                .iter()
+                .map(|profile| profile.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))),
+    }
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T, InstallerError> {
+    let contents = std::fs::read_to_string(path).map_err(|e| {
+        InstallerError::io(format!("Failed to read {}", path.display())).caused_by(e)
+    })?;
+    model::parse(serde_json::from_str(&contents)?, "launch json")
+}
+
+/// The libraries on the class path, Ornithe's taking precedence over vanilla
+/// ones of the same artifact, and the natives jars with the paths not to
+/// extract from them.
+fn libraries(
+    libraries_dir: &Path,
+    vanilla: &VersionJson,
+    ornithe: &LoaderProfile,
+) -> (Vec<PathBuf>, Vec<(PathBuf, Vec<String>)>) {
+    let mut artifacts = HashSet::new();
+    let mut classpath = Vec::new();
+    for library in &ornithe.libraries {
+        artifacts.insert(artifact_key(&library.name));
+        classpath.push(libraries_dir.join(server::split_artifact(&library.name)));
+    }
+    let mut natives = Vec::new();
+    for library in vanilla
+        .libraries
+        .iter()
+        .filter(|library| library.is_allowed())
+    {
+        let downloads = library.downloads.as_ref();
+        if let Some(classifier) = library.native_classifier() {
+            let path = downloads
+                .and_then(|downloads| downloads.classifiers.as_ref())
+                .and_then(|classifiers| classifiers.get(&classifier))
+                .and_then(|native| native.path.clone())
+                .unwrap_or_else(|| {
+                    server::split_artifact(&(library.name.clone() + ":" + &classifier))
+                });
+            let exclude = library
+                .extra
+                .get("extract")
+                .and_then(|extract| extract.get("exclude"))
+                .and_then(|exclude| exclude.as_array())
+                .map(|exclude| {
+                    exclude
+                        .iter()
+                        .filter_map(|path| path.as_str().map(str::to_owned))
+                        .collect()
+                })
+                .unwrap_or_default();
+            natives.push((libraries_dir.join(path), exclude));
+        }
+        let artifact = downloads.and_then(|downloads| downloads.artifact.as_ref());
+        if (artifact.is_none() && library.natives.is_some())
+            || !artifacts.insert(artifact_key(&library.name))
+        {
+            continue;
+        }
+        let path = artifact
+            .and_then(|artifact| artifact.path.clone())
+            .unwrap_or_else(|| server::split_artifact(&library.name));
+        classpath.push(libraries_dir.join(path));
+    }
+    (classpath, natives)
+}
+
+/// The maven coordinates of a library without its version.
+fn artifact_key(name: &str) -> String {
+    name.split(':')
+        .enumerate()
+        .filter(|(index, _)| *index != 2)
+        .map(|(_, part)| part)
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+fn missing_files(profile: &ClientProfile, missing: &[PathBuf]) -> InstallerError {
+    InstallerError::NotFound(format!(
+        "{} game files of {} are missing, e.g. {}. Install it with --offline-ready first",
+        missing.len(),
+        profile.name,
+        missing[0].display()
+    ))
+}
+
+/// Replaces the natives directory with the contents of the natives jars.
+fn extract_natives(
+    natives: &[(PathBuf, Vec<String>)],
+    natives_dir: &Path,
+) -> Result<(), InstallerError> {
+    if natives_dir.exists() {
+        std::fs::remove_dir_all(natives_dir)?;
+    }
+    std::fs::create_dir_all(natives_dir)?;
+    for (jar, exclude) in natives {
+        let mut archive = ZipArchive::new(File::open(jar)?).map_err(|e| {
+            InstallerError::parse(format!("Failed to open natives {}", jar.display())).caused_by(e)
+        })?;
+        for index in 0..archive.len() {
+            let mut entry = archive.by_index(index).map_err(|e| {
+                InstallerError::parse(format!("Failed to read natives {}", jar.display()))
+                    .caused_by(e)
+            })?;
+            if entry.is_dir() || exclude.iter().any(|path| entry.name().starts_with(path)) {
+                continue;
+            }
+            let Some(path) = entry.enclosed_name() else {
+                continue;
+            };
+            let target = natives_dir.join(path);
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::io::copy(&mut entry, &mut File::create(target)?)?;
+        }
+    }
+    Ok(())
+}
+
+/// Copies the assets of versions that don't read them from the object store to
+/// where they look for them, returning the directory to pass as `game_assets`.
+fn prepare_assets(
+    id: &str,
+    index: &AssetObjects,
+    assets_dir: &Path,
+    game_dir: &Path,
+) -> Result<PathBuf, InstallerError> {
+    let target = if index.map_to_resources {
+        game_dir.join("resources")
+    } else if index.is_virtual {
+        assets_dir.join("virtual").join(id)
+    } else {
+        return Ok(assets_dir.to_owned());
+    };
+    for (name, object) in &index.objects {
+        let path = target.join(name);
+        if path.is_file() {
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::copy(object.path(assets_dir), path)?;
+    }
+    Ok(target)
+}
+
+/// The arguments whose rules allow them on the running system.
+fn resolve(arguments: &[Argument]) -> Vec<String> {
+    arguments
+        .iter()
+        .flat_map(|argument| match argument {
+            Argument::Plain(argument) => vec![argument.clone()],
+            Argument::Conditional { rules, value } if rules_allow(rules) => match value {
+                ArgumentValue::Single(argument) => vec![argument.clone()],
+                ArgumentValue::Multiple(arguments) => arguments.clone(),
+            },
+            Argument::Conditional { .. } => Vec::new(),
+        })
+        .collect()
+}
+
+/// Replaces the `${name}` placeholders the launcher fills in, leaving
+/// unknown ones as they are. The argument is read once from left to right,
+/// so placeholders inside substituted values are kept as they are.
+fn substitute(argument: &str, values: &HashMap<&str, String>) -> String {
+    let mut out = String::with_capacity(argument.len());
+    let mut rest = argument;
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let placeholder = &rest[start..];
+        let Some(end) = placeholder.find('}') else {
+            rest = placeholder;
+            break;
+        };
+        match values.get(&placeholder[2..end]) {
+            Some(value) => out.push_str(value),
+            None => out.push_str(&placeholder[..=end]),
+        }
+        rest = &placeholder[end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// The uuid offline servers give a player, `UUID.nameUUIDFromBytes` of
+/// `OfflinePlayer:<name>` (md5, version 3).
+fn offline_uuid(username: &str) -> String {
+    let mut bytes: [u8; 16] = Md5::digest(format!("OfflinePlayer:{}", username)).into();
+    bytes[6] = (bytes[6] & 0x0f) | 0x30;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Quotes an argument for a POSIX shell if it needs to be.
+fn quote(argument: &str) -> String {
+    if !argument.is_empty()
+        && argument
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=,+@%".contains(c))
+    {
+        return argument.to_owned();
+    }
+    format!("'{}'", argument.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn substitute_replaces_placeholders_once() {
+        let values = HashMap::from([
+            ("auth_player_name", "${version_name}".to_owned()),
+            ("version_name", "1.8.9".to_owned()),
+        ]);
+        assert_eq!(
+            substitute("--username ${auth_player_name}", &values),
+            "--username ${version_name}"
+        );
+        assert_eq!(
+            substitute("${version_name}-${unknown}-${version_name}", &values),
+            "1.8.9-${unknown}-1.8.9"
+        );
+        assert_eq!(
+            substitute("-Dname=${version_name", &values),
+            "-Dname=${version_name"
+        );
+    }
+
+    #[test]
+    fn offline_uuid_matches_offline_servers() {
+        assert_eq!(offline_uuid("Notch"), "b50ad385829d3141a2167e7d7539ba7f");
+    }
+}
@@ -0,0 +1,105 @@
+//! The profile files of the official launcher.
+
+use std::path::{Path, PathBuf};
+
+use log::warn;
+use serde_json::{Map, Value, json};
+
+use crate::errors::InstallerError;
+
+use super::plan::Plan;
+
+pub const LAUNCHER_PROFILES: &str = "launcher_profiles.json";
+/// Kept instead of or next to `launcher_profiles.json` by some launcher
+/// installs, such as the one from the Microsoft Store.
+const ALTERNATIVE_FILES: [&str; 1] = ["launcher_profiles_microsoft_store.json"];
+
+/// The profile files of a game directory that exist.
+pub fn existing_files(game_dir: &Path) -> Vec<PathBuf> {
+    std::iter::once(LAUNCHER_PROFILES)
+        .chain(ALTERNATIVE_FILES)
+        .map(|name| game_dir.join(name))
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+/// The profile files to add a profile to: those that exist, or a new
+/// `launcher_profiles.json` on a fresh launcher.
+pub fn files_to_update(game_dir: &Path) -> Vec<PathBuf> {
+    let files = existing_files(game_dir);
+    if files.is_empty() {
+        return vec![game_dir.join(LAUNCHER_PROFILES)];
+    }
+    files
+}
+
+pub struct LauncherProfiles {
+    pub path: PathBuf,
+    json: Value,
+    /// The original contents of a file that had to be repaired.
+    damaged: Option<String>,
+}
+
+impl LauncherProfiles {
+    /// Reads a profile file, starting with no profiles if it doesn't exist.
+    /// A file that isn't valid json or has no `profiles` object is repaired,
+    /// keeping the original contents next to it once saved.
+    pub fn load(path: &Path) -> Result<LauncherProfiles, InstallerError> {
+        let mut profiles = LauncherProfiles {
+            path: path.to_owned(),
+            json: json!({ "profiles": {} }),
+            damaged: None,
+        };
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(profiles),
+            Err(e) => {
+                return Err(
+                    InstallerError::io(format!("Failed to read {}", path.display())).caused_by(e),
+                );
+            }
+        };
+        match serde_json::from_str::<Value>(&contents) {
+            Ok(Value::Object(mut object)) => {
+                if !object.get("profiles").is_some_and(Value::is_object) {
+                    if object.contains_key("profiles") {
+                        profiles.damaged = Some(contents);
+                    }
+                    object.insert("profiles".to_owned(), Value::Object(Map::new()));
+                }
+                profiles.json = Value::Object(object);
+            }
+            Ok(_) => profiles.damaged = Some(contents),
+            Err(e) => {
+                warn!("Failed to parse {}: {}", path.display(), e);
+                profiles.damaged = Some(contents);
+            }
+        }
+        if profiles.damaged.is_some() {
+            warn!(
+                "Repairing {}, the original is kept as {}",
+                path.display(),
+                profiles.backup_path().display()
+            );
+        }
+        Ok(profiles)
+    }
+
+    pub fn profiles(&mut self) -> &mut Map<String, Value> {
+        // Ensured when the file is loaded
+        self.json["profiles"].as_object_mut().unwrap()
+    }
+
+    pub fn save(&self, plan: &mut Plan) -> Result<(), InstallerError> {
+        if let Some(damaged) = &self.damaged {
+            plan.write(&self.backup_path(), damaged)?;
+        }
+        plan.write(&self.path, serde_json::to_string(&self.json)?)
+    }
+
+    fn backup_path(&self) -> PathBuf {
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(".bak");
+        self.path.with_file_name(name)
+    }
+}
@@ -1,103 +1,181 @@
-use std::{fs::File, io::Write, path::PathBuf};
+use std::{
+    fs::File,
+    io::{Sink, Write},
+    path::{Path, PathBuf},
+};
 
 use log::info;
-use serde_json::{Value, json};
 use zip::{ZipWriter, write::SimpleFileOptions};
 
 use crate::{
+    actions::{
+        installs,
+        mods::ModSelection,
+        plan::Plan,
+        receipt::{RECEIPT_FILE, Receipt},
+    },
     errors::InstallerError,
+    java,
+    model::{
+        loader::LoaderLibrary,
+        mmc::{MmcComponent, MmcPack, MmcPatch, MmcRequirement},
+        version::{Library, LibraryDownloads},
+    },
     net::{
+        GameSide,
+        loaders::LoaderType,
         manifest::{self, MinecraftVersion},
-        meta::{self, LoaderType, LoaderVersion},
+        meta::{self, IntermediaryGen, LoaderVersion},
+        resolver::MetadataResolver,
     },
+    progress::Progress,
 };
 
-const INTERMEDIARY_PATCH: &str =
-    include_str!("../../res/packformat/patches/net.fabricmc.intermediary.json");
 const INSTANCE_CONFIG: &str = include_str!("../../res/packformat/instance.cfg");
-const MMC_PACK: &str = include_str!("../../res/packformat/mmc-pack.json");
 
+#[allow(clippy::too_many_arguments)]
 pub async fn install(
     version: MinecraftVersion,
     loader_type: LoaderType,
     loader_version: LoaderVersion,
+    intermediary_gen: IntermediaryGen,
     output_dir: PathBuf,
     copy_profile_path: bool,
     generate_zip: bool,
+    mods: ModSelection,
+    resolver: &MetadataResolver,
+    progress: Progress,
+    plan: &mut Plan,
 ) -> Result<(), InstallerError> {
-    if !output_dir.exists() {
-        std::fs::create_dir_all(&output_dir)?;
-    }
-    let output_dir = output_dir.canonicalize()?;
-
-    info!("Fetching version information...");
-    let version_id = version.get_id(&crate::net::GameSide::Client).await?;
-    let intermediary_versions = meta::fetch_intermediary_versions().await?;
-    let intermediary_version = intermediary_versions
-        .get(&version_id)
-        .ok_or(InstallerError(
-            "Could not find matching intermediary version".to_owned(),
-        ))?;
+    let output_dir = plan.create_dir(&output_dir)?;
+    plan.begin(&output_dir)?;
+
+    progress.phase("Fetching version information...");
+    let version_id = version.get_id(&GameSide::Client, resolver).await?;
+    let intermediary_versions = resolver.intermediary_versions(intermediary_gen).await?;
+    let intermediary_version =
+        intermediary_versions
+            .get(&version_id)
+            .ok_or(InstallerError::NotFound(
+                "Could not find matching intermediary version".to_owned(),
+            ))?;
 
     let intermediary_maven = intermediary_version
         .maven
         .clone()
         .strip_suffix(&(":".to_owned() + &intermediary_version.version))
-        .ok_or(InstallerError(
-            "Failed to retrieve intermediary maven coordinates".to_owned(),
+        .ok_or(InstallerError::parse(
+            "Failed to retrieve intermediary maven coordinates",
         ))?
         .to_owned();
 
-    let lwjgl_version = manifest::find_lwjgl_version(&version).await?;
+    let lwjgl_version = manifest::find_lwjgl_version(&version, resolver).await?;
 
-    info!("Transforming templates...");
+    let mods = mods.resolve(&version, &loader_type).await?;
 
-    let mut transformed_pack_json = serde_json::from_str::<Value>(
-        &transform_pack_json(
-            &version,
-            &loader_type,
-            &loader_version,
-            &lwjgl_version,
-            &intermediary_version.version,
-        )
-        .await?,
-    )?;
+    progress.phase("Transforming templates...");
 
-    let transformed_intermediary_patch =
-        transform_intermediary_patch(&version, &intermediary_version.version, &intermediary_maven)
-            .await?;
+    let mut pack_json = build_pack_json(
+        &version,
+        &loader_type,
+        &loader_version,
+        &lwjgl_version,
+        &intermediary_version.version,
+    );
+
+    let intermediary_patch = build_intermediary_patch(
+        &version,
+        &intermediary_version.version,
+        &intermediary_maven,
+        intermediary_gen,
+    );
 
-    let minecraft_patch_json = get_mmc_launch_json(&version, &lwjgl_version).await?;
+    let minecraft_patch_json = get_mmc_launch_json(&version, &lwjgl_version, resolver).await?;
 
     let output_file = if generate_zip {
-        output_dir.join("Ornithe-".to_owned() + &version.id + ".zip")
+        output_dir.join(installs::INSTANCE_PREFIX.to_owned() + &version.id + ".zip")
     } else {
-        let dir = output_dir.join("Ornithe-".to_owned() + &version.id);
+        // The instance is built in the staging directory and moved into place as a whole
+        let dir = output_dir.join(installs::INSTANCE_PREFIX.to_owned() + &version.id);
         if std::fs::exists(&dir).unwrap_or_default() {
-            return Err(InstallerError("Instance already exists".to_string()));
+            return Err(InstallerError::Conflict(format!(
+                "Instance {} already exists",
+                dir.display()
+            )));
         }
-        std::fs::create_dir_all(&dir)?;
         dir
     };
 
-    info!("Fetching library information...");
+    progress.phase("Fetching library information...");
+
+    let extra_libs = meta::fetch_profile_libraries(
+        intermediary_version,
+        &loader_type,
+        &loader_version,
+        intermediary_gen,
+        resolver,
+    )
+    .await?;
+
+    let mut receipt = Receipt::new(
+        &version.id,
+        &loader_type,
+        &loader_version,
+        Some(intermediary_version.version.clone()),
+        intermediary_gen,
+    );
+    receipt.add_source(crate::config::get().endpoints.version_json(&version.id));
+    receipt.add_source(
+        meta::launch_json_url(
+            &GameSide::Client,
+            &version,
+            &loader_type,
+            &loader_version.version,
+            intermediary_gen,
+            resolver,
+        )
+        .await?,
+    );
+    for source in mods.sources(&GameSide::Client) {
+        receipt.add_source(source);
+    }
 
-    let extra_libs =
-        meta::fetch_profile_libraries(&intermediary_version, &loader_type, &loader_version).await?;
+    let instance_dir = if generate_zip {
+        None
+    } else {
+        Some(plan.stage_dir(&output_file)?)
+    };
+    // Mods for a zip are downloaded to the staging directory and added once the zip is written
+    let game_dir = if generate_zip {
+        plan.scratch_dir(&version.id)?
+    } else {
+        output_file.join(".minecraft")
+    };
+    if !mods.is_empty() {
+        let written = mods
+            .install(&game_dir, &GameSide::Client, &progress, plan)
+            .await?;
+        // Files of a zip are recorded as they are added to it
+        if !generate_zip && !plan.is_dry_run() {
+            for file in written {
+                receipt.add_staged_file(&output_file, &file, &plan.source(&file))?;
+            }
+        }
+    }
 
-    let mut zip: Box<dyn Writer> = if generate_zip {
-        info!("Generating instance zip...");
+    let writer: Box<dyn Writer> = if plan.is_dry_run() {
+        Box::new(std::io::sink())
+    } else if generate_zip {
+        progress.phase("Generating instance zip...");
 
-        if std::fs::exists(&output_file).unwrap_or_default() {
-            std::fs::remove_file(&output_file)?;
-        }
-        let file = std::fs::File::create_new(&output_file)?;
+        let file = std::fs::File::create(plan.stage(&output_file)?)?;
         Box::new(ZipWriter::new(file))
     } else {
-        info!("Generating output files...");
+        progress.phase("Generating output files...");
 
-        Box::new(output_file.clone())
+        Box::new(instance_dir.unwrap_or_else(|| output_file.clone()))
     };
+    let mut zip = Recording { writer, receipt };
 
     let mut instance_cfg = INSTANCE_CONFIG.replace("${mc_version}", &version.id);
 
@@ -113,47 +191,53 @@ pub async fn install(
 
     zip.write_file(
         "patches/net.fabricmc.intermediary.json",
-        transformed_intermediary_patch.as_bytes(),
+        &serde_json::to_vec_pretty(&intermediary_patch)?,
     )?;
 
     zip.write_file(
         "patches/net.minecraft.json",
-        minecraft_patch_json.as_bytes(),
+        &serde_json::to_vec_pretty(&minecraft_patch_json)?,
     )?;
 
-    let pack_components = transformed_pack_json["components"].as_array_mut().unwrap();
     for library in extra_libs {
-        let colons = library
-            .name
-            .char_indices()
-            .filter(|c| c.1 == ':')
-            .map(|c| c.0);
-        let index = colons.clone().last().unwrap();
-        let uid = library.name.get(0..index).unwrap().replace(":", ".");
-        let lib_name = library
-            .name
-            .get((colons.clone().next().unwrap() + 1)..colons.clone().last().unwrap())
-            .unwrap();
-        let version = library.name.get(0..(colons.last().unwrap() + 1)).unwrap();
-        zip.write_file(&("patches/".to_owned() + &uid + ".json"), 
-            format!(r#"{{"formatVersion": 1, "libraries": [{{"name": "{}","url": "{}"}}], "name": "{}", "type": "release", "uid": "{}", "version": "{}"}}"#,
-             library.name, library.url, lib_name, uid, version).as_bytes())?;
-
-        pack_components.push(json!({
-            "cachedName": lib_name,
-            "cachedVersion": version,
-            "uid": uid
-        }));
+        let (patch, component) = library_patch(&library)?;
+        zip.write_file(
+            &("patches/".to_owned() + &component.uid + ".json"),
+            &serde_json::to_vec_pretty(&patch)?,
+        )?;
+        pack_json.components.push(component);
     }
 
-    zip.write_file(
-        "mmc-pack.json",
-        &serde_json::to_vec_pretty(&transformed_pack_json)?,
-    )?;
+    zip.write_file("mmc-pack.json", &serde_json::to_vec_pretty(&pack_json)?)?;
+
+    if generate_zip && game_dir.exists() {
+        add_dir(&mut zip, &game_dir, ".minecraft")?;
+    }
+
+    let Recording {
+        mut writer,
+        receipt,
+    } = zip;
+    writer.write_file(RECEIPT_FILE, &receipt.to_vec()?)?;
+    // Zips are finished when their writer is dropped
+    drop(writer);
+
+    if plan.is_dry_run() {
+        if generate_zip {
+            plan.record_write(&output_file);
+        } else {
+            for file in receipt.file_paths(&output_file) {
+                plan.record_write(&file);
+            }
+            plan.record_write(&output_file.join(RECEIPT_FILE));
+        }
+        return Ok(());
+    }
+    plan.commit()?;
 
     if copy_profile_path {
         cli_clipboard::set_contents(output_file.to_string_lossy().into_owned())
-            .map_err(|_| InstallerError("Failed to copy profile path".to_owned()))?;
+            .map_err(|e| InstallerError::other(format!("Failed to copy profile path: {}", e)))?;
     }
 
     info!("Done!");
@@ -161,128 +245,219 @@ pub async fn install(
     Ok(())
 }
 
-async fn transform_intermediary_patch(
+/// Builds the patch and the component that add a loader library to an instance.
+pub fn library_patch(library: &LoaderLibrary) -> Result<(MmcPatch, MmcComponent), InstallerError> {
+    let (uid, lib_name, version) = split_library_name(&library.name)?;
+    let mut patch = MmcPatch::new(&uid, lib_name, version);
+    patch
+        .libraries
+        .push(Library::maven(&library.name, &library.url));
+    let component = MmcComponent {
+        cached_name: Some(lib_name.to_owned()),
+        cached_version: Some(version.to_owned()),
+        uid,
+        ..Default::default()
+    };
+    Ok((patch, component))
+}
+
+/// Splits maven coordinates into the uid, name and version of the component
+/// providing them.
+fn split_library_name(name: &str) -> Result<(String, &str, &str), InstallerError> {
+    let mut parts = name.splitn(3, ':');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(group), Some(artifact), Some(version)) => {
+            Ok((group.to_owned() + "." + artifact, artifact, version))
+        }
+        _ => Err(InstallerError::parse(format!(
+            "Invalid maven coordinates for library {}",
+            name
+        ))),
+    }
+}
+
+fn build_intermediary_patch(
     version: &MinecraftVersion,
-    intermediary_version: &String,
-    intermediary_maven: &String,
-) -> Result<String, InstallerError> {
-    Ok(INTERMEDIARY_PATCH
-        .replace("${mc_version}", &version.id)
-        .replace("${intermediary_ver}", &intermediary_version)
-        .replace("${intermediary_maven}", &intermediary_maven))
+    intermediary_version: &str,
+    intermediary_maven: &str,
+    intermediary_gen: IntermediaryGen,
+) -> MmcPatch {
+    let mut patch = MmcPatch::new(
+        "net.fabricmc.intermediary",
+        "Intermediary Mappings",
+        intermediary_version,
+    );
+    patch.libraries.push(Library::maven(
+        &(intermediary_maven.to_owned() + ":" + intermediary_version),
+        crate::config::get().endpoints.maven(),
+    ));
+    patch.requires.push(MmcRequirement {
+        equals: Some(version.id.clone()),
+        uid: "net.minecraft".to_owned(),
+        ..Default::default()
+    });
+    patch.intermediary_generation = Some(intermediary_gen.0);
+    patch
+}
+
+fn lwjgl_uid(lwjgl_version: &str) -> &'static str {
+    if lwjgl_version.starts_with('3') {
+        "org.lwjgl3"
+    } else {
+        "org.lwjgl"
+    }
 }
 
-async fn transform_pack_json(
+fn build_pack_json(
     version: &MinecraftVersion,
     loader_type: &LoaderType,
     loader_version: &LoaderVersion,
-    lwjgl_version: &String,
-    intermediary_version: &String,
-) -> Result<String, InstallerError> {
-    let lwjgl_major = lwjgl_version.chars().next().unwrap();
-    Ok(MMC_PACK
-        .replace("${mc_version}", &version.id)
-        .replace("${intermediary_ver}", &intermediary_version)
-        .replace("${loader_version}", &loader_version.version)
-        .replace(
-            "${loader_name}",
-            &(loader_type.get_localized_name().to_owned() + " Loader"),
-        )
-        .replace("${loader_uid}", loader_type.get_maven_uid())
-        .replace("${lwjgl_version}", &lwjgl_version)
-        .replace("${lwjgl_major_ver}", &lwjgl_major.to_string())
-        .replace(
-            "${lwjgl_uid}",
-            if lwjgl_major == '3' {
-                "org.lwjgl3"
-            } else {
-                "org.lwjgl"
-            },
-        ))
+    lwjgl_version: &str,
+    intermediary_version: &str,
+) -> MmcPack {
+    let lwjgl_major = lwjgl_version.split('.').next().unwrap_or(lwjgl_version);
+    let mut pack = MmcPack::new();
+    pack.components.push(MmcComponent {
+        cached_name: Some("LWJGL ".to_owned() + lwjgl_major),
+        cached_version: Some(lwjgl_version.to_owned()),
+        cached_volatile: Some(true),
+        dependency_only: Some(true),
+        uid: lwjgl_uid(lwjgl_version).to_owned(),
+        version: Some(lwjgl_version.to_owned()),
+        ..Default::default()
+    });
+    pack.components.push(MmcComponent {
+        cached_name: Some("Minecraft".to_owned()),
+        cached_requires: vec![MmcRequirement {
+            suggests: Some(lwjgl_version.to_owned()),
+            uid: lwjgl_uid(lwjgl_version).to_owned(),
+            ..Default::default()
+        }],
+        cached_version: Some(version.id.clone()),
+        important: Some(true),
+        uid: "net.minecraft".to_owned(),
+        version: Some(version.id.clone()),
+        ..Default::default()
+    });
+    pack.components.push(MmcComponent {
+        cached_name: Some("Intermediary Mappings".to_owned()),
+        cached_requires: vec![MmcRequirement {
+            equals: Some(version.id.clone()),
+            uid: "net.minecraft".to_owned(),
+            ..Default::default()
+        }],
+        cached_version: Some(intermediary_version.to_owned()),
+        dependency_only: Some(true),
+        uid: "net.fabricmc.intermediary".to_owned(),
+        version: Some(version.id.clone()),
+        ..Default::default()
+    });
+    pack.components.push(MmcComponent {
+        cached_name: Some(loader_type.get_localized_name().to_owned() + " Loader"),
+        cached_requires: vec![MmcRequirement {
+            uid: "net.fabricmc.intermediary".to_owned(),
+            ..Default::default()
+        }],
+        cached_version: Some(loader_version.version.clone()),
+        uid: loader_type.get_maven_uid().to_owned(),
+        version: Some(loader_version.version.clone()),
+        ..Default::default()
+    });
+    pack
 }
 
 async fn get_mmc_launch_json(
     version: &MinecraftVersion,
-    lwjgl_version: &String,
-) -> Result<String, InstallerError> {
-    let client_name = format!("com.mojang:minecraft:{}:client", version.id);
-    let vanilla_json = serde_json::from_str::<Value>(&manifest::fetch_launch_json(version).await?)?;
-
-    let client = vanilla_json["downloads"]["client"].as_object().unwrap();
-
-    let main_jar = json!({
-        "downloads": {
-            "artifact": client
-        },
-        "name": client_name
-    });
+    lwjgl_version: &str,
+    resolver: &MetadataResolver,
+) -> Result<MmcPatch, InstallerError> {
+    let vanilla_json = manifest::fetch_launch_json(version, resolver).await?;
+
+    let client = vanilla_json
+        .downloads
+        .as_ref()
+        .map(|downloads| downloads.client.clone())
+        .ok_or_else(|| {
+            InstallerError::parse(format!(
+                "Version json of {} has no client download",
+                version.id
+            ))
+        })?;
+    let main_jar = Library {
+        downloads: Some(LibraryDownloads {
+            artifact: Some(client),
+            classifiers: None,
+        }),
+        url: None,
+        ..Library::maven(&format!("com.mojang:minecraft:{}:client", version.id), "")
+    };
 
-    let mut libraries = vanilla_json["libraries"].clone();
-    let vanilla_libraries = libraries.as_array_mut().unwrap();
-    vanilla_libraries.retain(|lib| {
-        let name = lib["name"].as_str().unwrap_or_default();
-        !name.contains("org.ow2.asm") && !name.contains("org.lwjgl")
-    });
+    let mut libraries = vanilla_json.libraries.clone();
+    libraries.retain(|lib| !lib.name.contains("org.ow2.asm") && !lib.name.contains("org.lwjgl"));
 
     let mut traits = Vec::new();
 
-    if vanilla_json["mainClass"]
-        .as_str()
-        .unwrap_or_default()
-        .contains("launchwrapper")
+    if vanilla_json.main_class.contains("launchwrapper") {
+        traits.push("texturepacks".to_owned());
+    }
+
+    if vanilla_json
+        .arguments
+        .as_ref()
+        .is_some_and(|arguments| !arguments.game.is_empty())
     {
-        traits.push("texturepacks");
+        traits.push("FirstThreadOnMacOs".to_owned());
     }
 
-    let mut minecraft_arguments = vanilla_json["minecraftArguments"]
-        .as_str()
-        .unwrap_or("")
-        .to_owned();
-    if let Some(game_arguments) = vanilla_json["arguments"]["game"].as_array() {
-        if !game_arguments.is_empty() {
-            let mut combined = String::new();
-            for arg in game_arguments {
-                if arg.is_string() {
-                    combined += &(arg.as_str().unwrap().to_owned() + " ");
-                }
-            }
-            minecraft_arguments = combined.trim().to_owned();
+    let mut patch = MmcPatch::new("net.minecraft", "Minecraft", &version.id);
+    patch.asset_index = vanilla_json.asset_index.clone();
+    patch.compatible_java_majors = vec![java::required_major(vanilla_json.java_version.as_ref())];
+    patch.libraries = libraries;
+    patch.main_class = Some(vanilla_json.main_class.clone());
+    patch.main_jar = Some(main_jar);
+    patch.minecraft_arguments = Some(vanilla_json.game_arguments());
+    patch.release_time = vanilla_json.release_time.clone();
+    patch.requires.push(MmcRequirement {
+        suggests: Some(lwjgl_version.to_owned()),
+        uid: lwjgl_uid(lwjgl_version).to_owned(),
+        ..Default::default()
+    });
+    patch.traits = traits;
+    patch.release_type = Some(vanilla_json.release_type.clone());
 
-            traits.push("FirstThreadOnMacOs");
+    Ok(patch)
+}
+
+/// Copies a directory into the instance, below the given path.
+fn add_dir(zip: &mut dyn Writer, dir: &Path, path: &str) -> Result<(), InstallerError> {
+    zip.create_dir(path)?;
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = path.to_owned() + "/" + &entry.file_name().to_string_lossy();
+        if entry.file_type()?.is_dir() {
+            add_dir(zip, &entry.path(), &name)?;
+        } else {
+            zip.write_file(&name, &std::fs::read(entry.path())?)?;
         }
     }
+    Ok(())
+}
 
-    let lwjgl_major = lwjgl_version.chars().next().unwrap();
-    let mut json = json!({
-        "assetIndex": vanilla_json["assetIndex"],
-        "compatibleJavaMajors": [8, 17, 21],
-        "formatVersion":1,
-        "libraries": vanilla_libraries,
-        "mainClass": vanilla_json["mainClass"],
-        "mainJar": main_jar,
-        "minecraftArguments": minecraft_arguments,
-        "name":"Minecraft",
-        "releaseTime": vanilla_json["releaseTime"],
-        "requires": [{
-            "suggests": lwjgl_version,
-            "uid": if lwjgl_major == '3' {
-                "org.lwjgl3"
-            } else {
-                "org.lwjgl"
-            }
-        }],
-        "type":vanilla_json["type"],
-        "uid":"net.minecraft",
-        "version": &version.id
-    });
+/// Records the files written to the instance in its receipt.
+struct Recording {
+    writer: Box<dyn Writer>,
+    receipt: Receipt,
+}
 
-    if !traits.is_empty() {
-        json.as_object_mut()
-            .unwrap()
-            .insert("+traits".to_owned(), json!(traits));
+impl Writer for Recording {
+    fn write_file(&mut self, path: &str, buf: &[u8]) -> Result<(), InstallerError> {
+        self.receipt.add_bytes(path, buf);
+        self.writer.write_file(path, buf)
     }
 
-    Ok(serde_json::to_string_pretty(&json)?)
+    fn create_dir(&mut self, path: &str) -> Result<(), InstallerError> {
+        self.writer.create_dir(path)
+    }
 }
 
 trait Writer {
@@ -306,6 +481,17 @@ impl Writer for PathBuf {
     }
 }
 
+/// Discards the instance, for dry runs.
+impl Writer for Sink {
+    fn write_file(&mut self, _path: &str, _buf: &[u8]) -> Result<(), InstallerError> {
+        Ok(())
+    }
+
+    fn create_dir(&mut self, _path: &str) -> Result<(), InstallerError> {
+        Ok(())
+    }
+}
+
 impl Writer for ZipWriter<File> {
     fn write_file(&mut self, path: &str, buf: &[u8]) -> Result<(), InstallerError> {
         self.start_file(path, SimpleFileOptions::default())?;
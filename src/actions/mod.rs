@@ -1,3 +1,14 @@
 pub mod client;
+pub mod game_files;
+pub mod installs;
+pub mod inventory;
+pub mod launch;
+pub mod launcher_profiles;
 pub mod mmc_pack;
+pub mod mods;
+pub mod mrpack;
+pub mod plan;
+pub mod receipt;
 pub mod server;
+pub mod uninstall;
+pub mod update;
@@ -0,0 +1,499 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use log::{info, warn};
+use tokio::task::JoinSet;
+
+use crate::{
+    errors::InstallerError,
+    net::{
+        GameSide,
+        integrity::{Integrity, sha1_file},
+        loaders::LoaderType,
+        manifest::MinecraftVersion,
+        modrinth,
+    },
+    progress::Progress,
+};
+
+use super::{mrpack::Modpack, plan::Plan};
+
+/// Modrinth project of the Ornithe Standard Libraries.
+const OSL_PROJECT: &str = "osl";
+/// Maven group and artifact of the Ornithe Standard Libraries.
+const OSL_MAVEN_PATH: &str = "net/ornithemc/osl";
+
+/// Where the Ornithe Standard Libraries are downloaded from.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum OslSource {
+    /// The Modrinth-compatible api configured as `endpoints.modrinth`.
+    #[default]
+    Modrinth,
+    /// The Ornithe maven repository.
+    Maven,
+}
+
+impl FromStr for OslSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "modrinth" => Ok(OslSource::Modrinth),
+            "maven" => Ok(OslSource::Maven),
+            _ => Err(format!("'{}' is not a source, use modrinth or maven", s)),
+        }
+    }
+}
+
+/// What to install into the game directory besides the loader.
+#[derive(Clone, Debug, Default)]
+pub struct ModSelection {
+    pub osl: Option<OslSource>,
+    pub modpack: Option<Modpack>,
+    /// Modrinth project slugs or ids.
+    pub mods: Vec<String>,
+}
+
+impl ModSelection {
+    pub fn is_empty(&self) -> bool {
+        self.osl.is_none() && self.modpack.is_none() && self.mods.is_empty()
+    }
+
+    /// Looks up everything that has to be downloaded, so missing mods are
+    /// reported before the installation touches any files.
+    pub async fn resolve(
+        self,
+        version: &MinecraftVersion,
+        loader_type: &LoaderType,
+    ) -> Result<ResolvedMods, InstallerError> {
+        let osl = match self.osl {
+            Some(source) => Some(resolve_osl(source, version, loader_type).await?),
+            None => None,
+        };
+        let mods = resolve_mods(&self.mods, osl.as_ref(), version, loader_type).await?;
+        Ok(ResolvedMods {
+            osl,
+            modpack: self.modpack,
+            mods,
+        })
+    }
+}
+
+/// A [`ModSelection`] ready to be installed.
+pub struct ResolvedMods {
+    osl: Option<ModDownload>,
+    modpack: Option<Modpack>,
+    mods: Vec<ModDownload>,
+}
+
+impl ResolvedMods {
+    pub fn is_empty(&self) -> bool {
+        self.osl.is_none() && self.modpack.is_none() && self.mods.is_empty()
+    }
+
+    /// The urls everything is downloaded from.
+    pub fn sources(&self, side: &GameSide) -> Vec<String> {
+        let mut sources = self
+            .modpack
+            .as_ref()
+            .map(|modpack| modpack.download_urls(side))
+            .unwrap_or_default();
+        sources.extend(
+            self.osl
+                .iter()
+                .chain(&self.mods)
+                .map(|download| download.url.clone()),
+        );
+        sources
+    }
+
+    /// Installs the modpack and mods into a game directory, returning the
+    /// files written.
+    pub async fn install(
+        &self,
+        game_dir: &Path,
+        side: &GameSide,
+        progress: &Progress,
+        plan: &mut Plan,
+    ) -> Result<Vec<PathBuf>, InstallerError> {
+        let mut written = Vec::new();
+        if let Some(modpack) = &self.modpack {
+            written.extend(modpack.install(game_dir, side, progress, plan).await?);
+        }
+        if let Some(osl) = &self.osl {
+            let file =
+                install_osl(&game_dir.join("mods"), osl, &mut written, progress, plan).await?;
+            written.push(file);
+        }
+        if !self.mods.is_empty() {
+            written.extend(install_mods(&game_dir.join("mods"), &self.mods, progress, plan).await?);
+        }
+        Ok(written)
+    }
+}
+
+/// A mod jar to be placed in a mods folder.
+#[derive(Clone, Debug)]
+pub struct ModDownload {
+    /// The Modrinth project, if the file comes from Modrinth.
+    pub project_id: Option<String>,
+    pub file_name: String,
+    pub url: String,
+    pub integrity: Integrity,
+}
+
+/// A mod to resolve, and the mod that requires it.
+enum Requirement {
+    Project(String, Option<String>),
+    Version(String, Option<String>),
+}
+
+/// Resolves Modrinth projects and their required dependencies to the newest
+/// files supporting the game version and loader.
+///
+/// Every mod that can't be resolved is collected, so they are reported together.
+/// If `osl` is selected, mods depending on the Ornithe Standard Libraries use
+/// that build instead of resolving another one.
+async fn resolve_mods(
+    ids: &[String],
+    osl: Option<&ModDownload>,
+    version: &MinecraftVersion,
+    loader_type: &LoaderType,
+) -> Result<Vec<ModDownload>, InstallerError> {
+    let mut queue: VecDeque<Requirement> = ids
+        .iter()
+        .map(|id| Requirement::Project(id.clone(), None))
+        .collect();
+    let mut seen = HashSet::new();
+    let mut resolved_projects = HashSet::new();
+    if let Some(osl) = osl {
+        seen.insert(OSL_PROJECT.to_owned());
+        if let Some(project_id) = &osl.project_id {
+            seen.insert(project_id.clone());
+            resolved_projects.insert(project_id.clone());
+        }
+    }
+    let mut resolved = Vec::new();
+    let mut failures = Vec::new();
+
+    while let Some(requirement) = queue.pop_front() {
+        let (id, required_by) = match &requirement {
+            Requirement::Project(id, required_by) | Requirement::Version(id, required_by) => {
+                (id, required_by)
+            }
+        };
+        if !seen.insert(id.clone()) {
+            continue;
+        }
+        let found = match &requirement {
+            Requirement::Project(..) => {
+                modrinth::fetch_project_versions(id, &version.id, loader_type.get_name())
+                    .await
+                    .and_then(|versions| {
+                        versions.into_iter().next().ok_or_else(|| {
+                            InstallerError::NotFound(format!(
+                                "no version supports Minecraft {} with {} Loader",
+                                version.id,
+                                loader_type.get_localized_name()
+                            ))
+                        })
+                    })
+            }
+            Requirement::Version(..) => modrinth::fetch_version(id).await,
+        };
+        let found = match found {
+            Ok(found) => found,
+            Err(e) => {
+                failures.push(match required_by {
+                    Some(parent) => format!("{} (required by {}): {}", id, parent, e.report()),
+                    None => format!("{}: {}", id, e.report()),
+                });
+                continue;
+            }
+        };
+        // Projects are requested by slug or id, so duplicates only show after resolving
+        if !resolved_projects.insert(found.project_id.clone()) {
+            continue;
+        }
+        let Some(file) = found.primary_file() else {
+            failures.push(format!(
+                "{}: version {} has no files",
+                id, found.version_number
+            ));
+            continue;
+        };
+        // The maven build doesn't name its project, so it is recognized by the file
+        if osl.is_some() && is_osl_jar(&file.filename) {
+            info!(
+                "Skipping {}, the Ornithe Standard Libraries are already selected",
+                id
+            );
+            continue;
+        }
+        info!("Resolved {} to {}", id, file.filename);
+        resolved.push(ModDownload {
+            project_id: Some(found.project_id.clone()),
+            file_name: file.filename.clone(),
+            url: file.url.clone(),
+            integrity: file.integrity(),
+        });
+        for dependency in &found.dependencies {
+            if dependency.dependency_type != "required" {
+                continue;
+            }
+            let parent = Some(id.clone());
+            if let Some(version_id) = &dependency.version_id {
+                queue.push_back(Requirement::Version(version_id.clone(), parent));
+            } else if let Some(project_id) = &dependency.project_id {
+                queue.push_back(Requirement::Project(project_id.clone(), parent));
+            }
+        }
+    }
+
+    if !failures.is_empty() {
+        return Err(InstallerError::NotFound(format!(
+            "Could not resolve {} mod(s):\n  {}",
+            failures.len(),
+            failures.join("\n  ")
+        )));
+    }
+    Ok(resolved)
+}
+
+/// Finds the build of the Ornithe Standard Libraries for a game version.
+async fn resolve_osl(
+    source: OslSource,
+    version: &MinecraftVersion,
+    loader_type: &LoaderType,
+) -> Result<ModDownload, InstallerError> {
+    let not_found = || {
+        InstallerError::NotFound(format!(
+            "Could not find Ornithe Standard Libraries for Minecraft {}",
+            version.id
+        ))
+    };
+    match source {
+        OslSource::Modrinth => {
+            let versions =
+                modrinth::fetch_project_versions(OSL_PROJECT, &version.id, loader_type.get_name())
+                    .await?;
+            let osl = versions.first().ok_or_else(not_found)?;
+            let file = osl.primary_file().ok_or_else(not_found)?;
+            Ok(ModDownload {
+                project_id: Some(osl.project_id.clone()),
+                file_name: file.filename.clone(),
+                url: file.url.clone(),
+                integrity: file.integrity(),
+            })
+        }
+        OslSource::Maven => {
+            let base = crate::config::get().endpoints.maven().to_owned() + "/" + OSL_MAVEN_PATH;
+            let metadata = crate::net::fetch_text(&(base.clone() + "/maven-metadata.xml")).await?;
+            let osl_version = maven_versions(&metadata)
+                .filter(|v| supports_game_version(v, &version.id))
+                .last()
+                .ok_or_else(not_found)?;
+            let file_name = format!("osl-{}.jar", osl_version);
+            let url = format!("{}/{}/{}", base, osl_version, file_name);
+            let integrity = super::server::fetch_sha1(&url).await?;
+            Ok(ModDownload {
+                project_id: None,
+                file_name,
+                url,
+                integrity,
+            })
+        }
+    }
+}
+
+/// The `<version>` entries of a `maven-metadata.xml`, in the listed order.
+fn maven_versions(metadata: &str) -> impl Iterator<Item = &str> {
+    metadata
+        .split("<version>")
+        .skip(1)
+        .filter_map(|part| part.split_once("</version>"))
+        .map(|(version, _)| version.trim())
+}
+
+/// Whether the build metadata of an OSL version (e.g. `0.16.3+mc1.8.9`)
+/// names the game version.
+fn supports_game_version(osl_version: &str, game_version: &str) -> bool {
+    osl_version.split_once('+').is_some_and(|(_, build)| {
+        build
+            .split("-mc")
+            .any(|v| v.trim_start_matches("mc") == game_version)
+    })
+}
+
+/// Downloads the Ornithe Standard Libraries into a mods folder, replacing
+/// previously installed builds and those among the files already written,
+/// e.g. by a modpack.
+async fn install_osl(
+    mods_dir: &Path,
+    osl: &ModDownload,
+    written: &mut Vec<PathBuf>,
+    progress: &Progress,
+    plan: &mut Plan,
+) -> Result<PathBuf, InstallerError> {
+    progress.phase("Installing Ornithe Standard Libraries..");
+    check_file_name(osl)?;
+    let is_old_osl = |path: &Path| {
+        path.parent() == Some(mods_dir)
+            && path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name != osl.file_name && is_osl_jar(name))
+    };
+    plan.create_dir(mods_dir)?;
+    if mods_dir.is_dir() {
+        for entry in std::fs::read_dir(mods_dir)? {
+            let path = entry?.path();
+            if is_old_osl(&path) {
+                info!("Removing {}", path.display());
+                plan.remove(&path)?;
+            }
+        }
+    }
+    let (old, kept) = std::mem::take(written)
+        .into_iter()
+        .partition::<Vec<_>, _>(|path| is_old_osl(path));
+    *written = kept;
+    for path in old {
+        info!("Skipping {}, replaced by {}", path.display(), osl.file_name);
+        plan.discard_write(&path)?;
+    }
+    let file = mods_dir.join(&osl.file_name);
+    let Some(target) = plan.download(&osl.url, &file)? else {
+        return Ok(file);
+    };
+    progress.totals(1, osl.integrity.size);
+    download(&target, osl, progress).await?;
+    info!("Installed {}", osl.file_name);
+    Ok(file)
+}
+
+/// Whether a file name is that of a build of the Ornithe Standard Libraries.
+fn is_osl_jar(name: &str) -> bool {
+    name.starts_with("osl-") && name.ends_with(".jar")
+}
+
+/// Downloads mods into a mods folder, replacing the jars of other versions of
+/// the same projects.
+async fn install_mods(
+    mods_dir: &Path,
+    mods: &[ModDownload],
+    progress: &Progress,
+    plan: &mut Plan,
+) -> Result<Vec<PathBuf>, InstallerError> {
+    progress.phase("Installing mods..");
+    for old_file in find_replaced_mods(mods_dir, mods).await? {
+        info!("Removing {}", old_file.display());
+        plan.remove(&old_file)?;
+    }
+    progress.totals(
+        mods.len(),
+        mods.iter().map(|download| download.integrity.size).sum(),
+    );
+    let mut downloads = JoinSet::new();
+    let mut files = Vec::new();
+    for mod_download in mods {
+        check_file_name(mod_download)?;
+        let file = mods_dir.join(&mod_download.file_name);
+        if let Some(target) = plan.download(&mod_download.url, &file)? {
+            let mod_download = mod_download.clone();
+            let progress = progress.clone();
+            downloads.spawn(async move { download(&target, &mod_download, &progress).await });
+        }
+        files.push(file);
+    }
+    while let Some(done) = downloads.join_next().await {
+        done??;
+    }
+    info!("Installed {} mods", files.len());
+    Ok(files)
+}
+
+/// Finds the jars in a mods folder that belong to the Modrinth projects of
+/// the given mods, but are a different file. Jars Modrinth doesn't know, or
+/// that can't be looked up, are kept.
+async fn find_replaced_mods(
+    mods_dir: &Path,
+    mods: &[ModDownload],
+) -> Result<Vec<PathBuf>, InstallerError> {
+    let projects: HashSet<&str> = mods
+        .iter()
+        .filter_map(|download| download.project_id.as_deref())
+        .collect();
+    let file_names: HashSet<&str> = mods
+        .iter()
+        .map(|download| download.file_name.as_str())
+        .collect();
+    if projects.is_empty() || !mods_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut jars = Vec::new();
+    for entry in std::fs::read_dir(mods_dir)? {
+        let path = entry?.path();
+        let is_candidate = path.is_file()
+            && path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.ends_with(".jar") && !file_names.contains(name));
+        if is_candidate {
+            jars.push((sha1_file(&path)?, path));
+        }
+    }
+    if jars.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let sha1s = jars
+        .iter()
+        .map(|(sha1, _)| sha1.clone())
+        .collect::<Vec<_>>();
+    let versions = match modrinth::fetch_versions_by_hashes(&sha1s).await {
+        Ok(versions) => versions,
+        Err(e) => {
+            warn!(
+                "Failed to look up the jars in {}, keeping them: {}",
+                mods_dir.display(),
+                e.report()
+            );
+            return Ok(Vec::new());
+        }
+    };
+    let mut replaced = jars
+        .into_iter()
+        .filter(|(sha1, _)| {
+            versions
+                .get(sha1)
+                .is_some_and(|version| projects.contains(version.project_id.as_str()))
+        })
+        .map(|(_, path)| path)
+        .collect::<Vec<_>>();
+    replaced.sort();
+    Ok(replaced)
+}
+
+async fn download(
+    file: &Path,
+    download: &ModDownload,
+    progress: &Progress,
+) -> Result<(), InstallerError> {
+    crate::net::download_file(&download.url, file, &download.integrity, progress).await
+}
+
+/// Makes sure a file name from an api response can't point outside the mods folder.
+fn check_file_name(download: &ModDownload) -> Result<(), InstallerError> {
+    if Path::new(&download.file_name).file_name() != Some(download.file_name.as_ref()) {
+        return Err(InstallerError::parse(format!(
+            "Invalid file name {}",
+            download.file_name
+        )));
+    }
+    Ok(())
+}
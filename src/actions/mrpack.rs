@@ -0,0 +1,554 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::Write,
+    path::{Component, Path, PathBuf},
+};
+
+use log::{info, warn};
+use serde_json::Map;
+use tokio::task::JoinSet;
+use zip::{ZipArchive, ZipWriter, write::SimpleFileOptions};
+
+use crate::{
+    actions::{
+        installs::{self, InstalledVersions},
+        plan::Plan,
+    },
+    errors::InstallerError,
+    model::{
+        self,
+        mrpack::{EnvSupport, FORMAT_VERSION, INDEX_FILE, MrpackFile, MrpackHashes, MrpackIndex},
+    },
+    net::{
+        GameSide,
+        integrity::{Integrity, sha1_file, sha512_file},
+        loaders::LoaderType,
+        meta::IntermediaryGen,
+        modrinth,
+    },
+    progress::Progress,
+};
+
+/// Dependency key of the game version.
+pub const MINECRAFT_DEPENDENCY: &str = "minecraft";
+/// Dependency key of the intermediary version, written by the installer's own exports.
+pub const INTERMEDIARY_DEPENDENCY: &str = "ornithe-intermediary";
+/// Dependency key of the intermediary generation, written by the installer's
+/// own exports. Packs without it use generation 1.
+pub const INTERMEDIARY_GEN_DEPENDENCY: &str = "ornithe-intermediary-generation";
+
+const OVERRIDES: &str = "overrides";
+
+/// A `.mrpack` file and its parsed index.
+#[derive(Clone, Debug)]
+pub struct Modpack {
+    path: PathBuf,
+    pub index: MrpackIndex,
+}
+
+impl Modpack {
+    pub fn open(path: &Path) -> Result<Modpack, InstallerError> {
+        let mut zip = ZipArchive::new(File::open(path).map_err(|e| {
+            InstallerError::io(format!("Failed to open modpack {}", path.display())).caused_by(e)
+        })?)?;
+        let index_file = zip.by_name(INDEX_FILE).map_err(|_| {
+            InstallerError::parse(format!(
+                "{} is not a modpack, it has no {}",
+                path.display(),
+                INDEX_FILE
+            ))
+        })?;
+        let index: MrpackIndex = model::parse(serde_json::from_reader(index_file)?, INDEX_FILE)?;
+        if index.format_version != FORMAT_VERSION {
+            return Err(InstallerError::parse(format!(
+                "Unsupported modpack format version {}",
+                index.format_version
+            )));
+        }
+        if index.game != "minecraft" {
+            return Err(InstallerError::parse(format!(
+                "Modpack is for {}, not Minecraft",
+                index.game
+            )));
+        }
+        // Reject packs writing outside the game directory before anything is installed
+        for file in &index.files {
+            relative_path(&file.path)?;
+        }
+        for i in 0..zip.len() {
+            let entry = zip.by_index(i)?;
+            if entry.enclosed_name().is_none() {
+                return Err(escapes(entry.name()));
+            }
+        }
+        Ok(Modpack {
+            path: path.to_owned(),
+            index,
+        })
+    }
+
+    pub fn minecraft_version(&self) -> Result<&str, InstallerError> {
+        self.index
+            .dependencies
+            .get(MINECRAFT_DEPENDENCY)
+            .map(String::as_str)
+            .ok_or_else(|| InstallerError::parse("Modpack does not specify a Minecraft version"))
+    }
+
+    /// The loader the pack requires and its version, if it names one.
+    /// Loaders are matched by their meta path, e.g. `fabric-loader`.
+    pub fn loader(&self) -> Result<Option<(LoaderType, &str)>, InstallerError> {
+        let loaders = &crate::config::get().loaders;
+        for (key, version) in &self.index.dependencies {
+            if [
+                MINECRAFT_DEPENDENCY,
+                INTERMEDIARY_DEPENDENCY,
+                INTERMEDIARY_GEN_DEPENDENCY,
+            ]
+            .contains(&key.as_str())
+            {
+                continue;
+            }
+            return match loaders.all().iter().find(|l| l.get_meta_path() == key) {
+                Some(loader) => Ok(Some((loader.clone(), version))),
+                None => Err(InstallerError::NotFound(format!(
+                    "Modpack requires {}, which is not a supported loader",
+                    key
+                ))),
+            };
+        }
+        Ok(None)
+    }
+
+    pub fn intermediary_gen(&self) -> Result<IntermediaryGen, InstallerError> {
+        match self.index.dependencies.get(INTERMEDIARY_GEN_DEPENDENCY) {
+            Some(generation) => generation.parse().map_err(InstallerError::parse),
+            None => Ok(IntermediaryGen::default()),
+        }
+    }
+
+    /// The urls the files the side supports are downloaded from.
+    pub fn download_urls(&self, side: &GameSide) -> Vec<String> {
+        self.index
+            .files
+            .iter()
+            .filter(|file| supports(file, side))
+            .filter_map(|file| file.downloads.first().cloned())
+            .collect()
+    }
+
+    /// Downloads the files of the pack that the side supports into the game
+    /// directory and extracts its overrides on top.
+    pub async fn install(
+        &self,
+        game_dir: &Path,
+        side: &GameSide,
+        progress: &Progress,
+        plan: &mut Plan,
+    ) -> Result<Vec<PathBuf>, InstallerError> {
+        progress.phase(format!("Installing modpack {}..", self.index.name));
+
+        let files = self
+            .index
+            .files
+            .iter()
+            .filter(|file| supports(file, side))
+            .map(|file| Ok((file.clone(), game_dir.join(relative_path(&file.path)?))))
+            .collect::<Result<Vec<_>, InstallerError>>()?;
+
+        progress.totals(
+            files.len(),
+            Some(files.iter().map(|(file, _)| file.file_size).sum()),
+        );
+        let mut downloads = JoinSet::new();
+        let mut written = Vec::new();
+        for (file, output) in files {
+            let url = file.downloads.first().cloned().unwrap_or_default();
+            if let Some(target) = plan.download(&url, &output)? {
+                let progress = progress.clone();
+                downloads.spawn(async move { download(&file, &target, &progress).await });
+            }
+            written.push(output);
+        }
+        while let Some(done) = downloads.join_next().await {
+            done??;
+        }
+
+        progress.phase("Applying modpack overrides..");
+        let side_overrides = match side {
+            GameSide::Client => "client-overrides",
+            GameSide::Server => "server-overrides",
+        };
+        let mut zip = ZipArchive::new(File::open(&self.path)?)?;
+        // Side specific overrides replace the common ones
+        for folder in [OVERRIDES, side_overrides] {
+            for i in 0..zip.len() {
+                let mut entry = zip.by_index(i)?;
+                let name = entry.enclosed_name().ok_or_else(|| escapes(entry.name()))?;
+                let Ok(relative) = name.strip_prefix(folder) else {
+                    continue;
+                };
+                if entry.is_dir() || relative.as_os_str().is_empty() {
+                    continue;
+                }
+                let output = game_dir.join(relative);
+                let target = plan.stage(&output)?;
+                if !plan.is_dry_run() {
+                    std::io::copy(&mut entry, &mut File::create(&target)?)?;
+                }
+                written.push(output);
+            }
+        }
+
+        info!(
+            "Installed modpack {} {}",
+            self.index.name, self.index.version_id
+        );
+        Ok(written)
+    }
+}
+
+fn supports(file: &MrpackFile, side: &GameSide) -> bool {
+    let Some(env) = file.env else {
+        return true;
+    };
+    let support = match side {
+        GameSide::Client => env.client,
+        GameSide::Server => env.server,
+    };
+    support != EnvSupport::Unsupported
+}
+
+/// Checks that a path from the index stays inside the game directory.
+fn relative_path(path: &str) -> Result<PathBuf, InstallerError> {
+    let path = PathBuf::from(path);
+    if path.as_os_str().is_empty()
+        || !path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Err(escapes(&path.to_string_lossy()));
+    }
+    Ok(path)
+}
+
+fn escapes(path: &str) -> InstallerError {
+    InstallerError::parse(format!("Modpack path {} escapes the game directory", path))
+}
+
+async fn download(
+    file: &MrpackFile,
+    output: &Path,
+    progress: &Progress,
+) -> Result<(), InstallerError> {
+    let integrity = Integrity {
+        sha1: Some(file.hashes.sha1.clone()),
+        sha512: Some(file.hashes.sha512.clone()),
+        size: Some(file.file_size),
+        ..Default::default()
+    };
+    let mut last_error = None;
+    for url in &file.downloads {
+        match crate::net::download_file(url, output, &integrity, progress).await {
+            Ok(()) => return Ok(()),
+            Err(e) => {
+                warn!(
+                    "Failed to download {} from {}: {}",
+                    file.path,
+                    url,
+                    e.report()
+                );
+                last_error = Some(e);
+            }
+        }
+    }
+    Err(last_error.unwrap_or_else(|| {
+        InstallerError::parse(format!("Modpack file {} has no downloads", file.path))
+    }))
+}
+
+/// Packages a MultiMC/Prism instance generated by the installer, or a game
+/// directory with an Ornithe profile, as a `.mrpack`.
+///
+/// Jars in `mods/` that Modrinth knows by their hash are referenced by url,
+/// all others are bundled into the overrides together with `config/`.
+pub async fn export(
+    source: &Path,
+    profile: Option<&str>,
+    output: &Path,
+    name: String,
+    version_id: String,
+    progress: &Progress,
+) -> Result<(), InstallerError> {
+    progress.phase("Reading installation..");
+    let (versions, game_dir) = if source.join("mmc-pack.json").is_file() {
+        let game_dir = [".minecraft", "minecraft"]
+            .iter()
+            .map(|dir| source.join(dir))
+            .find(|dir| dir.is_dir())
+            .unwrap_or_else(|| source.join(".minecraft"));
+        (installs::read_instance(source)?, game_dir)
+    } else if source.join("versions").is_dir() {
+        (read_game_dir(source, profile)?, source.to_owned())
+    } else {
+        return Err(InstallerError::NotFound(format!(
+            "{} is neither a MultiMC/Prism instance nor a game directory",
+            source.display()
+        )));
+    };
+
+    let intermediary_version = versions.intermediary_version.ok_or_else(|| {
+        InstallerError::NotFound(format!(
+            "Could not find the intermediary version of {}",
+            source.display()
+        ))
+    })?;
+    let mut dependencies = BTreeMap::new();
+    dependencies.insert(MINECRAFT_DEPENDENCY.to_owned(), versions.minecraft);
+    dependencies.insert(
+        versions.loader.get_meta_path().to_owned(),
+        versions.loader_version,
+    );
+    dependencies.insert(INTERMEDIARY_DEPENDENCY.to_owned(), intermediary_version);
+    dependencies.insert(
+        INTERMEDIARY_GEN_DEPENDENCY.to_owned(),
+        versions.intermediary_gen.0.to_string(),
+    );
+
+    progress.phase("Looking up mods..");
+    let mut jars = Vec::new();
+    if let Ok(entries) = std::fs::read_dir(game_dir.join("mods")) {
+        for entry in entries {
+            let path = entry?.path();
+            if path.is_file() && path.extension().is_some_and(|ext| ext == "jar") {
+                jars.push(path);
+            }
+        }
+    }
+    jars.sort();
+    progress.totals(jars.len(), None);
+
+    let mut files = Vec::new();
+    let mut bundled = Vec::new();
+    for jar in jars {
+        let file_name = jar.file_name().unwrap_or_default().to_string_lossy();
+        let sha1 = sha1_file(&jar)?;
+        match modrinth::fetch_version_by_hash(&sha1).await {
+            Ok(version) => match version
+                .files
+                .iter()
+                .find(|f| f.hashes.sha1.as_ref() == Some(&sha1))
+            {
+                Some(file) => files.push(MrpackFile {
+                    path: "mods/".to_owned() + &file_name,
+                    hashes: MrpackHashes {
+                        sha1,
+                        sha512: sha512_file(&jar)?,
+                        extra: Map::new(),
+                    },
+                    env: None,
+                    downloads: vec![file.url.clone()],
+                    file_size: std::fs::metadata(&jar)?.len(),
+                }),
+                None => bundled.push(jar.clone()),
+            },
+            Err(InstallerError::NotFound(message)) => {
+                info!(
+                    "{} is not available on Modrinth, bundling it: {}",
+                    file_name, message
+                );
+                bundled.push(jar.clone());
+            }
+            Err(e) => return Err(e),
+        }
+        progress.file_finished(&jar, None);
+    }
+
+    let index = MrpackIndex {
+        format_version: FORMAT_VERSION,
+        game: "minecraft".to_owned(),
+        version_id,
+        name,
+        summary: None,
+        files,
+        dependencies,
+        extra: Map::new(),
+    };
+
+    progress.phase("Writing modpack..");
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut zip = ZipWriter::new(File::create(output)?);
+    zip.start_file(INDEX_FILE, SimpleFileOptions::default())?;
+    zip.write_all(&serde_json::to_vec_pretty(&index)?)?;
+    for jar in &bundled {
+        let name = jar.file_name().unwrap_or_default().to_string_lossy();
+        zip.start_file(
+            format!("{}/mods/{}", OVERRIDES, name),
+            SimpleFileOptions::default(),
+        )?;
+        std::io::copy(&mut File::open(jar)?, &mut zip)?;
+    }
+    let config = game_dir.join("config");
+    if config.is_dir() {
+        add_overrides(&mut zip, &config, &(OVERRIDES.to_owned() + "/config"))?;
+    }
+    zip.finish()?;
+
+    info!(
+        "Exported {} mods ({} bundled) to {}",
+        index.files.len() + bundled.len(),
+        bundled.len(),
+        output.display()
+    );
+    Ok(())
+}
+
+fn add_overrides(zip: &mut ZipWriter<File>, dir: &Path, path: &str) -> Result<(), InstallerError> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = path.to_owned() + "/" + &entry.file_name().to_string_lossy();
+        if entry.file_type()?.is_dir() {
+            add_overrides(zip, &entry.path(), &name)?;
+        } else {
+            zip.start_file(name, SimpleFileOptions::default())?;
+            std::io::copy(&mut File::open(entry.path())?, zip)?;
+        }
+    }
+    Ok(())
+}
+
+/// Reads the versions from an Ornithe profile in the `versions` folder of a
+/// game directory. Without a profile name, the directory must contain exactly one.
+fn read_game_dir(
+    game_dir: &Path,
+    profile: Option<&str>,
+) -> Result<InstalledVersions, InstallerError> {
+    let profile = match profile {
+        Some(profile) => profile.to_owned(),
+        None => {
+            let mut profiles = Vec::new();
+            for entry in std::fs::read_dir(game_dir.join("versions"))? {
+                let name = entry?.file_name().to_string_lossy().into_owned();
+                if name.ends_with(installs::PROFILE_SUFFIX) {
+                    profiles.push(name);
+                }
+            }
+            match profiles.len() {
+                0 => {
+                    return Err(InstallerError::NotFound(format!(
+                        "{} contains no Ornithe profile",
+                        game_dir.display()
+                    )));
+                }
+                1 => profiles.remove(0),
+                _ => {
+                    profiles.sort();
+                    return Err(InstallerError::Conflict(format!(
+                        "{} contains several Ornithe profiles, select one of: {}",
+                        game_dir.display(),
+                        profiles.join(", ")
+                    )));
+                }
+            }
+        }
+    };
+    installs::read_profile(game_dir, &profile)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes a pack with a single listed file and the given extra entries.
+    fn write_pack(name: &str, file_path: &str, entries: &[&str]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "ornithe-mrpack-{}-{}.mrpack",
+            name,
+            std::process::id()
+        ));
+        let index = serde_json::json!({
+            "formatVersion": FORMAT_VERSION,
+            "game": "minecraft",
+            "versionId": "1.0.0",
+            "name": "Test",
+            "files": [{
+                "path": file_path,
+                "hashes": { "sha1": "", "sha512": "" },
+                "downloads": [],
+                "fileSize": 0
+            }],
+            "dependencies": { "minecraft": "1.8.9" }
+        });
+        let mut zip = ZipWriter::new(File::create(&path).unwrap());
+        zip.start_file(INDEX_FILE, SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(index.to_string().as_bytes()).unwrap();
+        for entry in entries {
+            zip.start_file(*entry, SimpleFileOptions::default())
+                .unwrap();
+        }
+        zip.finish().unwrap();
+        path
+    }
+
+    fn open(name: &str, file_path: &str, entries: &[&str]) -> Result<Modpack, InstallerError> {
+        let path = write_pack(name, file_path, entries);
+        let modpack = Modpack::open(&path);
+        std::fs::remove_file(path).unwrap();
+        modpack
+    }
+
+    fn escapes_game_dir<T>(result: Result<T, InstallerError>) -> bool {
+        result.is_err_and(|e| e.to_string().contains("escapes the game directory"))
+    }
+
+    #[test]
+    fn relative_path_stays_inside_the_game_directory() {
+        assert_eq!(
+            relative_path("mods/mod.jar").unwrap(),
+            PathBuf::from("mods/mod.jar")
+        );
+        for path in [
+            "",
+            "../mod.jar",
+            "mods/../../mod.jar",
+            "./mod.jar",
+            "/mod.jar",
+        ] {
+            assert!(
+                escapes_game_dir(relative_path(path)),
+                "{} was accepted",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn open_accepts_paths_inside_the_game_directory() {
+        let modpack = open("valid", "mods/mod.jar", &["overrides/config/mod.json"]).unwrap();
+        assert_eq!(modpack.minecraft_version().unwrap(), "1.8.9");
+    }
+
+    #[test]
+    fn open_rejects_files_outside_the_game_directory() {
+        assert!(escapes_game_dir(open("file-parent", "../mod.jar", &[])));
+        assert!(escapes_game_dir(open("file-absolute", "/mod.jar", &[])));
+    }
+
+    #[test]
+    fn open_rejects_entries_outside_the_game_directory() {
+        assert!(escapes_game_dir(open(
+            "entry-parent",
+            "mods/mod.jar",
+            &["overrides/../../evil"]
+        )));
+        assert!(escapes_game_dir(open(
+            "entry-absolute",
+            "mods/mod.jar",
+            &["/evil"]
+        )));
+    }
+}
@@ -0,0 +1,705 @@
+//! The changes an installation makes to the disk. They are recorded as they
+//! are carried out, or only recorded for a dry run.
+//!
+//! Once an installation has begun in its root directory, files are staged in
+//! a directory below it and only moved into place on commit, so a failed or
+//! cancelled installation leaves the previous one untouched. Every step of a
+//! commit is journaled, so a commit that was killed is undone by the next
+//! installation in the same root.
+
+use std::{
+    fmt::Display,
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::errors::InstallerError;
+
+/// Directory below the root of an installation its files are staged in.
+pub const STAGING_DIR: &str = ".ornithe-staging";
+/// File in the staging directory the steps of a running commit are appended to.
+const JOURNAL_FILE: &str = "journal";
+/// Directory in the staging directory unfinished downloads are kept in, so a
+/// later installation can resume them.
+const PARTIAL_DIR: &str = "partial";
+
+#[derive(Serialize, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Plan {
+    #[serde(skip)]
+    dry_run: bool,
+    /// The installation root, once the installation has begun.
+    #[serde(skip)]
+    root: Option<PathBuf>,
+    /// Files and directories to move from the staging directory on commit.
+    #[serde(skip)]
+    staged: Vec<PathBuf>,
+    pub deletions: Vec<PathBuf>,
+    pub writes: Vec<PathBuf>,
+    pub downloads: Vec<PlannedDownload>,
+    pub launcher_profiles: Vec<ProfileEdit>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PlannedDownload {
+    pub url: String,
+    pub path: PathBuf,
+}
+
+/// An entry of `launcher_profiles.json` pointed to a version.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileEdit {
+    pub file: PathBuf,
+    pub key: String,
+    pub last_version_id: String,
+    /// Whether the entry is added rather than updated.
+    pub created: bool,
+}
+
+/// A step of a commit, undone in reverse order on failure.
+#[derive(Serialize, Deserialize, Debug)]
+enum Undo {
+    /// Moves a backup back to where it was taken from.
+    Restore {
+        backup: PathBuf,
+        original: PathBuf,
+    },
+    Remove(PathBuf),
+}
+
+impl Plan {
+    /// A plan that only records the changes, leaving the disk untouched.
+    pub fn dry_run() -> Plan {
+        let mut plan = Plan::default();
+        plan.dry_run = true;
+        plan
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// Starts staging the changes below an existing installation root. Until
+    /// then, and for plans that are never begun, changes are made in place.
+    ///
+    /// An installation that was killed while committing is undone first. If
+    /// that fails, its backups are kept and the installation is refused.
+    pub fn begin(&mut self, root: &Path) -> Result<(), InstallerError> {
+        if !self.dry_run {
+            let staging = root.join(STAGING_DIR);
+            // Left over from an installation that was killed
+            if staging.exists() {
+                recover(&staging)?;
+                remove_staging(&staging, true)?;
+            }
+            std::fs::create_dir_all(staging.join("files"))?;
+        }
+        self.root = Some(root.to_owned());
+        Ok(())
+    }
+
+    /// Creates a directory and its parents, returning its absolute path.
+    /// Directories of a staged installation are created on commit.
+    pub fn create_dir(&mut self, dir: &Path) -> Result<PathBuf, InstallerError> {
+        if dir.exists() {
+            return Ok(dir.canonicalize()?);
+        }
+        if self.dry_run || self.is_staging() {
+            return Ok(std::path::absolute(dir)?);
+        }
+        std::fs::create_dir_all(dir)?;
+        Ok(dir.canonicalize()?)
+    }
+
+    /// Removes a file or a directory with its contents, if it exists.
+    pub fn remove(&mut self, path: &Path) -> Result<(), InstallerError> {
+        if !path.exists() {
+            return Ok(());
+        }
+        self.deletions.push(path.to_owned());
+        if self.dry_run || self.is_staging() {
+            return Ok(());
+        }
+        if path.is_dir() {
+            std::fs::remove_dir_all(path)?;
+        } else {
+            std::fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    pub fn write(&mut self, path: &Path, contents: impl AsRef<[u8]>) -> Result<(), InstallerError> {
+        let target = self.stage(path)?;
+        if !self.dry_run {
+            std::fs::write(target, contents)?;
+        }
+        Ok(())
+    }
+
+    /// Records a file written by other means, e.g. extracted from an archive,
+    /// returning where to write it to.
+    pub fn stage(&mut self, path: &Path) -> Result<PathBuf, InstallerError> {
+        self.writes.push(path.to_owned());
+        self.stage_path(path)
+    }
+
+    /// Takes back a file written or downloaded earlier in the plan, so it is
+    /// not moved into place.
+    pub fn discard_write(&mut self, path: &Path) -> Result<(), InstallerError> {
+        self.writes.retain(|write| write != path);
+        self.downloads.retain(|download| download.path != path);
+        if self.dry_run {
+            return Ok(());
+        }
+        let written = match self.staged_path(path) {
+            Some(staged) => {
+                self.staged.retain(|staged| staged != path);
+                staged
+            }
+            None => path.to_owned(),
+        };
+        if written.is_file() {
+            std::fs::remove_file(written)?;
+        }
+        Ok(())
+    }
+
+    /// Records a file written without being staged, e.g. for dry runs of
+    /// installations that are not written file by file.
+    pub fn record_write(&mut self, path: &Path) {
+        self.writes.push(path.to_owned());
+    }
+
+    /// Returns where to build a directory that is moved into place as a whole.
+    pub fn stage_dir(&mut self, dir: &Path) -> Result<PathBuf, InstallerError> {
+        if self.dry_run {
+            return Ok(std::path::absolute(dir)?);
+        }
+        let staged = self.stage_path(dir)?;
+        std::fs::create_dir_all(&staged)?;
+        Ok(staged)
+    }
+
+    /// Returns a directory for intermediate files, which is removed together
+    /// with the staging directory.
+    pub fn scratch_dir(&self, name: &str) -> Result<PathBuf, InstallerError> {
+        let Some(root) = &self.root else {
+            return Err(InstallerError::other(
+                "The installation has not begun in a directory",
+            ));
+        };
+        let dir = root.join(STAGING_DIR).join("scratch").join(name);
+        if !self.dry_run {
+            std::fs::create_dir_all(&dir)?;
+        }
+        Ok(dir)
+    }
+
+    /// Records a download, returning where to download it to unless this is
+    /// a dry run. Files already in place are linked there, so they are reused
+    /// if they are up to date.
+    pub fn download(&mut self, url: &str, path: &Path) -> Result<Option<PathBuf>, InstallerError> {
+        self.downloads.push(PlannedDownload {
+            url: crate::net::resolve_url(url),
+            path: path.to_owned(),
+        });
+        if self.dry_run {
+            return Ok(None);
+        }
+        let target = self.stage_path(path)?;
+        if target != path && path.is_file() && !target.exists() {
+            link_or_copy(path, &target)?;
+        } else if let Some(partial) = self.partial_path(path)
+            && partial.is_file()
+        {
+            debug!("Resuming the partial download of {}", path.display());
+            std::fs::rename(&partial, crate::net::part_file(&target))?;
+        }
+        Ok(Some(target))
+    }
+
+    /// Where the contents of a file are before the plan is committed.
+    pub fn source(&self, path: &Path) -> PathBuf {
+        match self.staged_path(path) {
+            Some(staged) if staged.exists() => staged,
+            _ => path.to_owned(),
+        }
+    }
+
+    pub fn edit_launcher_profile(&mut self, edit: ProfileEdit) {
+        self.launcher_profiles.push(edit);
+    }
+
+    /// Carries out the deletions and moves the staged files into place,
+    /// putting everything back the way it was if that fails.
+    pub fn commit(&mut self) -> Result<(), InstallerError> {
+        if self.dry_run || !self.is_staging() {
+            return Ok(());
+        }
+        let staging = self.staging_dir().unwrap();
+        let mut journal = Journal::create(&staging.join(JOURNAL_FILE))?;
+        let result = self.apply(&staging, &mut journal);
+        // Closed before it is removed, which Windows requires
+        let Journal { file, steps } = journal;
+        drop(file);
+        let mut restored = true;
+        if let Err(e) = &result {
+            warn!("Rolling back installation: {}", e.report());
+            for undo in steps.into_iter().rev() {
+                if let Err(e) = undo.apply() {
+                    warn!("Failed to roll back: {}", e);
+                    restored = false;
+                }
+            }
+        }
+        if restored {
+            // So a cleanup that is interrupted isn't undone by the next installation
+            std::fs::remove_file(staging.join(JOURNAL_FILE))?;
+        } else {
+            warn!("Keeping the backups in {}", staging.display());
+            self.root = None;
+        }
+        // Removes the backups after a success, the staged files after a failure
+        if result.is_ok() {
+            self.remove_staging(false);
+            self.root = None;
+        } else {
+            self.discard();
+        }
+        result
+    }
+
+    fn apply(&self, staging: &Path, journal: &mut Journal) -> Result<(), InstallerError> {
+        let backups = staging.join("backup");
+        for (index, path) in self.deletions.iter().enumerate() {
+            if !path.exists() || path.starts_with(staging) {
+                continue;
+            }
+            let backup = backups.join(index.to_string());
+            std::fs::create_dir_all(&backups)?;
+            journal.push(Undo::Restore {
+                backup: backup.clone(),
+                original: path.clone(),
+            })?;
+            std::fs::rename(path, &backup)?;
+        }
+        for (index, path) in self.staged.iter().enumerate() {
+            let Some(staged) = self.staged_path(path).filter(|staged| staged.exists()) else {
+                continue;
+            };
+            if let Some(parent) = path.parent()
+                && !parent.exists()
+            {
+                let created = parent
+                    .ancestors()
+                    .take_while(|dir| !dir.exists())
+                    .last()
+                    .unwrap_or(parent)
+                    .to_owned();
+                journal.push(Undo::Remove(created))?;
+                std::fs::create_dir_all(parent)?;
+            }
+            let backup = backups.join(format!("staged-{}", index));
+            let existed = path.exists();
+            if path.is_dir() {
+                std::fs::create_dir_all(&backups)?;
+                journal.push(Undo::Restore {
+                    backup: backup.clone(),
+                    original: path.clone(),
+                })?;
+                std::fs::rename(path, &backup)?;
+            } else if existed {
+                // Files are replaced in one rename, so launcher_profiles.json is never missing
+                std::fs::create_dir_all(&backups)?;
+                journal.push(Undo::Restore {
+                    backup: backup.clone(),
+                    original: path.clone(),
+                })?;
+                link_or_copy(path, &backup)?;
+            } else {
+                journal.push(Undo::Remove(path.clone()))?;
+            }
+            debug!("Moving {} into place", path.display());
+            std::fs::rename(&staged, path).map_err(|e| {
+                InstallerError::io(format!("Failed to move {} into place", path.display()))
+                    .caused_by(e)
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Removes the staging directory, leaving the installation as it was.
+    /// Unfinished downloads are kept for the next installation.
+    fn discard(&mut self) {
+        self.remove_staging(true);
+        self.root = None;
+    }
+
+    fn remove_staging(&self, keep_partial: bool) {
+        if let Some(staging) = self.staging_dir()
+            && let Err(e) = remove_staging(&staging, keep_partial)
+            && staging.exists()
+        {
+            warn!("Failed to remove {}: {}", staging.display(), e.report());
+        }
+    }
+
+    fn is_staging(&self) -> bool {
+        self.staging_dir().is_some()
+    }
+
+    fn staging_dir(&self) -> Option<PathBuf> {
+        self.root
+            .as_ref()
+            .filter(|_| !self.dry_run)
+            .map(|root| root.join(STAGING_DIR))
+    }
+
+    /// The staged counterpart of a path below the root.
+    fn staged_path(&self, path: &Path) -> Option<PathBuf> {
+        let staging = self.staging_dir()?;
+        if path.starts_with(&staging) {
+            return None;
+        }
+        let relative = path.strip_prefix(self.root.as_ref()?).ok()?;
+        Some(staging.join("files").join(relative))
+    }
+
+    /// Where an unfinished download of a path below the root is kept.
+    fn partial_path(&self, path: &Path) -> Option<PathBuf> {
+        let relative = path.strip_prefix(self.root.as_ref()?).ok()?;
+        Some(crate::net::part_file(
+            &self.staging_dir()?.join(PARTIAL_DIR).join(relative),
+        ))
+    }
+
+    fn stage_path(&mut self, path: &Path) -> Result<PathBuf, InstallerError> {
+        let Some(staged) = self.staged_path(path) else {
+            if !self.dry_run
+                && let Some(parent) = path.parent()
+            {
+                std::fs::create_dir_all(parent)?;
+            }
+            return Ok(path.to_owned());
+        };
+        // Files inside a staged directory are moved together with it
+        if !self.staged.iter().any(|dir| path.starts_with(dir)) {
+            self.staged.push(path.to_owned());
+        }
+        if let Some(parent) = staged.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        Ok(staged)
+    }
+}
+
+impl Drop for Plan {
+    fn drop(&mut self) {
+        // An installation that was cancelled or failed before committing
+        self.discard();
+    }
+}
+
+impl Undo {
+    /// Undoes the step. Steps that were never carried out, or were already
+    /// undone, are skipped, as the journal records them before they happen.
+    fn apply(self) -> std::io::Result<()> {
+        match self {
+            Undo::Restore { backup, original } => {
+                if !backup.exists() {
+                    return Ok(());
+                }
+                if original.is_dir() {
+                    std::fs::remove_dir_all(&original)?;
+                }
+                std::fs::rename(backup, original)
+            }
+            Undo::Remove(path) if path.is_dir() => std::fs::remove_dir_all(path),
+            Undo::Remove(path) if path.exists() => std::fs::remove_file(path),
+            Undo::Remove(_) => Ok(()),
+        }
+    }
+}
+
+/// The steps of a commit, also appended to a file as they are taken.
+struct Journal {
+    file: File,
+    steps: Vec<Undo>,
+}
+
+impl Journal {
+    fn create(path: &Path) -> Result<Journal, InstallerError> {
+        Ok(Journal {
+            file: File::create(path)?,
+            steps: Vec::new(),
+        })
+    }
+
+    fn push(&mut self, undo: Undo) -> Result<(), InstallerError> {
+        let mut line = serde_json::to_vec(&undo)?;
+        line.push(b'\n');
+        self.file.write_all(&line)?;
+        self.steps.push(undo);
+        Ok(())
+    }
+}
+
+/// Undoes the commit of an installation that was killed, from the journal it
+/// left in the staging directory.
+fn recover(staging: &Path) -> Result<(), InstallerError> {
+    let journal = staging.join(JOURNAL_FILE);
+    if !journal.is_file() {
+        return Ok(());
+    }
+    warn!("Restoring the files of an interrupted installation");
+    // The last line is incomplete if the installation was killed while writing it
+    let steps = std::fs::read_to_string(&journal)?
+        .lines()
+        .map_while(|line| serde_json::from_str::<Undo>(line).ok())
+        .collect::<Vec<_>>();
+    for undo in steps.into_iter().rev() {
+        undo.apply().map_err(|e| {
+            InstallerError::Conflict(format!(
+                "Failed to restore the files of an interrupted installation, \
+                 its backups are kept in {}: {}",
+                staging.display(),
+                e
+            ))
+        })?;
+    }
+    std::fs::remove_file(journal)?;
+    Ok(())
+}
+
+/// Removes a staging directory, optionally moving the unfinished downloads
+/// among its staged files to where later installations look for them.
+fn remove_staging(staging: &Path, keep_partial: bool) -> Result<(), InstallerError> {
+    if !staging.exists() {
+        return Ok(());
+    }
+    if !keep_partial {
+        std::fs::remove_dir_all(staging)?;
+        return Ok(());
+    }
+    let partial = staging.join(PARTIAL_DIR);
+    keep_partial_downloads(&staging.join("files"), &partial)?;
+    for entry in std::fs::read_dir(staging)? {
+        let path = entry?.path();
+        if path == partial {
+            continue;
+        }
+        if path.is_dir() {
+            std::fs::remove_dir_all(path)?;
+        } else {
+            std::fs::remove_file(path)?;
+        }
+    }
+    if std::fs::read_dir(&partial).is_ok_and(|mut entries| entries.next().is_none()) {
+        std::fs::remove_dir(&partial)?;
+    }
+    if std::fs::read_dir(staging)?.next().is_none() {
+        std::fs::remove_dir(staging)?;
+    }
+    Ok(())
+}
+
+fn keep_partial_downloads(dir: &Path, partial: &Path) -> Result<(), InstallerError> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Ok(());
+    };
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            keep_partial_downloads(&path, &partial.join(entry.file_name()))?;
+        } else if path.extension().is_some_and(|ext| ext == "part") {
+            std::fs::create_dir_all(partial)?;
+            std::fs::rename(&path, partial.join(entry.file_name()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Hard links a file, or copies it where that isn't supported.
+fn link_or_copy(from: &Path, to: &Path) -> std::io::Result<()> {
+    if std::fs::hard_link(from, to).is_err() {
+        std::fs::copy(from, to)?;
+    }
+    Ok(())
+}
+
+impl Display for Plan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.deletions.is_empty() {
+            writeln!(f, "Delete:")?;
+            for path in &self.deletions {
+                writeln!(f, "  {}", path.display())?;
+            }
+        }
+        if !self.writes.is_empty() {
+            writeln!(f, "Write:")?;
+            for path in &self.writes {
+                writeln!(f, "  {}", path.display())?;
+            }
+        }
+        if !self.downloads.is_empty() {
+            writeln!(f, "Download:")?;
+            for download in &self.downloads {
+                writeln!(f, "  {} -> {}", download.url, download.path.display())?;
+            }
+        }
+        if !self.launcher_profiles.is_empty() {
+            writeln!(f, "Launcher profiles:")?;
+            for edit in &self.launcher_profiles {
+                writeln!(
+                    f,
+                    "  {} \"{}\" starting {} in {}",
+                    if edit.created { "Add" } else { "Update" },
+                    edit.key,
+                    edit.last_version_id,
+                    edit.file.display()
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An empty directory below the system's temporary directory.
+    fn temp_root(name: &str) -> PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("ornithe-plan-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    fn read(path: impl AsRef<Path>) -> String {
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn commit_moves_staged_files_into_place() {
+        let root = temp_root("commit");
+        std::fs::write(root.join("replaced.txt"), "old").unwrap();
+        std::fs::write(root.join("deleted.txt"), "old").unwrap();
+
+        let mut plan = Plan::default();
+        plan.begin(&root).unwrap();
+        plan.write(&root.join("replaced.txt"), "new").unwrap();
+        plan.write(&root.join("dir/added.txt"), "new").unwrap();
+        plan.remove(&root.join("deleted.txt")).unwrap();
+        assert_eq!(read(root.join("replaced.txt")), "old");
+        assert!(root.join("deleted.txt").exists());
+        assert!(!root.join("dir").exists());
+
+        plan.commit().unwrap();
+        assert_eq!(read(root.join("replaced.txt")), "new");
+        assert_eq!(read(root.join("dir/added.txt")), "new");
+        assert!(!root.join("deleted.txt").exists());
+        assert!(!root.join(STAGING_DIR).exists());
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn failed_commit_is_rolled_back() {
+        let root = temp_root("rollback");
+        std::fs::write(root.join("replaced.txt"), "old").unwrap();
+        std::fs::write(root.join("deleted.txt"), "old").unwrap();
+        // A file where the last staged file needs a directory
+        std::fs::write(root.join("blocker"), "old").unwrap();
+
+        let mut plan = Plan::default();
+        plan.begin(&root).unwrap();
+        plan.remove(&root.join("deleted.txt")).unwrap();
+        plan.write(&root.join("replaced.txt"), "new").unwrap();
+        plan.write(&root.join("dir/added.txt"), "new").unwrap();
+        plan.write(&root.join("blocker/file.txt"), "new").unwrap();
+
+        assert!(plan.commit().is_err());
+        assert_eq!(read(root.join("replaced.txt")), "old");
+        assert_eq!(read(root.join("deleted.txt")), "old");
+        assert_eq!(read(root.join("blocker")), "old");
+        assert!(!root.join("dir").exists());
+        assert!(!root.join(STAGING_DIR).exists());
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn discarded_writes_are_not_moved_into_place() {
+        let root = temp_root("discard");
+        std::fs::write(root.join("kept.txt"), "old").unwrap();
+
+        let mut plan = Plan::default();
+        plan.begin(&root).unwrap();
+        plan.write(&root.join("kept.txt"), "new").unwrap();
+        plan.write(&root.join("dir/discarded.txt"), "new").unwrap();
+        plan.discard_write(&root.join("kept.txt")).unwrap();
+        plan.discard_write(&root.join("dir/discarded.txt")).unwrap();
+        assert!(plan.writes.is_empty());
+
+        plan.commit().unwrap();
+        assert_eq!(read(root.join("kept.txt")), "old");
+        assert!(!root.join("dir").exists());
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn begin_undoes_an_interrupted_commit() {
+        let root = temp_root("recover");
+        let staging = root.join(STAGING_DIR);
+        let backup = staging.join("backup/0");
+        std::fs::create_dir_all(backup.parent().unwrap()).unwrap();
+        // A commit killed after replacing one file and adding another
+        std::fs::write(&backup, "old").unwrap();
+        std::fs::write(root.join("replaced.txt"), "new").unwrap();
+        std::fs::write(root.join("added.txt"), "new").unwrap();
+        let mut journal = Journal::create(&staging.join(JOURNAL_FILE)).unwrap();
+        journal
+            .push(Undo::Restore {
+                backup,
+                original: root.join("replaced.txt"),
+            })
+            .unwrap();
+        journal.push(Undo::Remove(root.join("added.txt"))).unwrap();
+        drop(journal);
+
+        let mut plan = Plan::default();
+        plan.begin(&root).unwrap();
+        assert_eq!(read(root.join("replaced.txt")), "old");
+        assert!(!root.join("added.txt").exists());
+        assert!(!staging.join(JOURNAL_FILE).exists());
+        assert!(!staging.join("backup").exists());
+        drop(plan);
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn partial_downloads_are_kept_for_the_next_installation() {
+        let root = temp_root("partial");
+        let file = root.join("mods/mod.jar");
+
+        let mut plan = Plan::default();
+        plan.begin(&root).unwrap();
+        let target = plan.download("https://example.com/mod.jar", &file).unwrap();
+        std::fs::write(crate::net::part_file(&target.unwrap()), "half").unwrap();
+        drop(plan);
+        assert!(!file.exists());
+
+        let mut plan = Plan::default();
+        plan.begin(&root).unwrap();
+        let target = plan.download("https://example.com/mod.jar", &file).unwrap();
+        assert_eq!(read(crate::net::part_file(&target.unwrap())), "half");
+        drop(plan);
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,215 @@
+//! The `ornithe-install.json` receipt recording what an installation resolved
+//! and which files it wrote.
+
+use std::path::{Component, Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    errors::InstallerError,
+    model::loader::LoaderProfile,
+    net::{
+        integrity,
+        loaders::LoaderType,
+        meta::{IntermediaryGen, LoaderVersion},
+    },
+};
+
+pub const RECEIPT_FILE: &str = "ornithe-install.json";
+
+/// What an installation was set up with. Client receipts are kept in the
+/// profile directory, instance and server receipts at their root; the paths
+/// of client receipts are relative to the game directory.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Receipt {
+    pub installer_version: String,
+    pub installed_at: DateTime<Utc>,
+    pub minecraft_version: String,
+    pub loader_type: String,
+    pub loader_version: String,
+    pub intermediary_version: Option<String>,
+    pub intermediary_generation: u32,
+    /// Urls of the metadata and files the installation was fetched from.
+    #[serde(default)]
+    pub sources: Vec<String>,
+    #[serde(default)]
+    pub files: Vec<ReceiptFile>,
+    /// Keys of the entries added to `launcher_profiles.json`.
+    #[serde(default)]
+    pub launcher_profiles: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ReceiptFile {
+    /// Path relative to the root of the installation, separated by `/`.
+    pub path: String,
+    pub sha256: String,
+    pub size: u64,
+}
+
+impl Receipt {
+    pub fn new(
+        minecraft_version: &str,
+        loader_type: &LoaderType,
+        loader_version: &LoaderVersion,
+        intermediary_version: Option<String>,
+        intermediary_gen: IntermediaryGen,
+    ) -> Receipt {
+        Receipt {
+            installer_version: crate::VERSION.to_owned(),
+            installed_at: Utc::now(),
+            minecraft_version: minecraft_version.to_owned(),
+            loader_type: loader_type.get_name().to_owned(),
+            loader_version: loader_version.version.clone(),
+            intermediary_version,
+            intermediary_generation: intermediary_gen.0,
+            sources: Vec::new(),
+            files: Vec::new(),
+            launcher_profiles: Vec::new(),
+        }
+    }
+
+    /// Records a url, as rewritten to the mirror it is fetched from.
+    pub fn add_source(&mut self, url: impl AsRef<str>) {
+        let url = crate::net::resolve_url(url.as_ref());
+        if !self.sources.contains(&url) {
+            self.sources.push(url);
+        }
+    }
+
+    /// Records a file written below the root of the installation.
+    pub fn add_file(&mut self, root: &Path, file: &Path) -> Result<(), InstallerError> {
+        self.add_staged_file(root, file, file)
+    }
+
+    /// Records a file that is yet to be moved into place from where it was staged.
+    pub fn add_staged_file(
+        &mut self,
+        root: &Path,
+        file: &Path,
+        staged: &Path,
+    ) -> Result<(), InstallerError> {
+        let path = relative_path(root, file)?;
+        let entry = ReceiptFile {
+            path,
+            sha256: integrity::sha256_file(staged)?,
+            size: std::fs::metadata(staged)?.len(),
+        };
+        self.insert_file(entry);
+        Ok(())
+    }
+
+    /// Records a file by its contents, for files written into archives.
+    pub fn add_bytes(&mut self, path: &str, bytes: &[u8]) {
+        self.insert_file(ReceiptFile {
+            path: path.to_owned(),
+            sha256: format!("{:x}", Sha256::digest(bytes)),
+            size: bytes.len() as u64,
+        });
+    }
+
+    fn insert_file(&mut self, entry: ReceiptFile) {
+        self.files.retain(|file| file.path != entry.path);
+        self.files.push(entry);
+    }
+
+    /// Takes over what a previous receipt of the same installation recorded:
+    /// its files that still exist, its launcher profiles and its sources
+    /// except the stale ones.
+    pub fn carry_over(&mut self, previous: Receipt, root: &Path, is_stale: impl Fn(&str) -> bool) {
+        for file in previous.files {
+            let recorded = self.files.iter().any(|entry| entry.path == file.path);
+            if !recorded && resolve(root, &file.path).is_some_and(|path| path.is_file()) {
+                self.files.push(file);
+            }
+        }
+        for source in previous.sources {
+            if !is_stale(&source) {
+                self.add_source(source);
+            }
+        }
+        for key in previous.launcher_profiles {
+            if !self.launcher_profiles.contains(&key) {
+                self.launcher_profiles.push(key);
+            }
+        }
+    }
+
+    /// Resolves the recorded files against the root, skipping paths that would
+    /// lead outside of it.
+    pub fn file_paths(&self, root: &Path) -> Vec<PathBuf> {
+        self.files
+            .iter()
+            .filter_map(|file| resolve(root, &file.path))
+            .collect()
+    }
+
+    /// Reads the receipt of a directory, if it has one.
+    pub fn read(dir: &Path) -> Result<Option<Receipt>, InstallerError> {
+        let path = dir.join(RECEIPT_FILE);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        serde_json::from_str(&std::fs::read_to_string(&path)?)
+            .map(Some)
+            .map_err(|e| {
+                InstallerError::parse(format!("Failed to parse {}", path.display())).caused_by(e)
+            })
+    }
+
+    /// Reads the receipt of a directory, ignoring one that can't be read.
+    /// Installations made by older versions of the installer have none.
+    pub fn find(dir: &Path) -> Option<Receipt> {
+        Receipt::read(dir).unwrap_or_else(|e| {
+            warn!("Ignoring receipt: {}", e.report());
+            None
+        })
+    }
+
+    pub fn write(&self, dir: &Path) -> Result<(), InstallerError> {
+        std::fs::write(dir.join(RECEIPT_FILE), self.to_vec()?)?;
+        Ok(())
+    }
+
+    pub fn to_vec(&self) -> Result<Vec<u8>, InstallerError> {
+        Ok(serde_json::to_vec_pretty(self)?)
+    }
+}
+
+/// The version of the intermediary library a launch profile puts on the class path.
+pub fn intermediary_version(
+    profile: &LoaderProfile,
+    intermediary_gen: IntermediaryGen,
+) -> Option<String> {
+    let prefix = intermediary_gen.maven_artifact() + ":";
+    profile
+        .libraries
+        .iter()
+        .find_map(|library| library.name.strip_prefix(&prefix).map(str::to_owned))
+}
+
+fn resolve(root: &Path, path: &str) -> Option<PathBuf> {
+    let path = Path::new(path);
+    path.components()
+        .all(|component| matches!(component, Component::Normal(_)))
+        .then(|| root.join(path))
+}
+
+fn relative_path(root: &Path, file: &Path) -> Result<String, InstallerError> {
+    let relative = file.strip_prefix(root).map_err(|_| {
+        InstallerError::other(format!(
+            "{} is not inside {}",
+            file.display(),
+            root.display()
+        ))
+    })?;
+    Ok(relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/"))
+}
@@ -1,38 +1,68 @@
 use std::{
+    collections::HashMap,
     ffi::OsStr,
-    io::Write,
-    path::PathBuf,
+    io::{Cursor, Write},
+    path::{Path, PathBuf},
     process::{Command, Stdio},
 };
 
 use log::info;
-use serde_json::Value;
 use tokio::task::JoinSet;
 use zip::{ZipArchive, ZipWriter, write::SimpleFileOptions};
 
 use crate::{
+    actions::{
+        installs,
+        mods::ModSelection,
+        plan::Plan,
+        receipt::{self, Receipt},
+    },
     errors::InstallerError,
+    java,
     net::{
-        manifest::MinecraftVersion,
-        meta::{LoaderType, LoaderVersion},
+        GameSide,
+        integrity::Integrity,
+        loaders::LoaderType,
+        manifest::{self, MinecraftVersion},
+        meta::{IntermediaryGen, LoaderVersion},
+        resolver::MetadataResolver,
     },
+    progress::Progress,
 };
 
+/// Directory below the server downloaded Java runtimes are kept in, unless a
+/// shared runtimes directory is configured.
+pub const RUNTIMES_DIR: &str = "runtimes";
+
+#[allow(clippy::too_many_arguments)]
 pub async fn install(
     version: MinecraftVersion,
     loader_type: LoaderType,
     loader_version: LoaderVersion,
+    intermediary_gen: IntermediaryGen,
     location: PathBuf,
     install_server: bool,
+    mods: ModSelection,
+    resolver: &MetadataResolver,
+    progress: Progress,
+    plan: &mut Plan,
 ) -> Result<(), InstallerError> {
     install_path(
         &version,
         &loader_type,
         &loader_version,
+        intermediary_gen,
         &location,
         install_server,
+        mods,
+        resolver,
+        &progress,
+        plan,
     )
     .await?;
+    if plan.is_dry_run() {
+        return Ok(());
+    }
 
     info!(
         "Installed Ornithe Server for Minecraft {} using {} Loader {} to {}",
@@ -45,17 +75,21 @@ pub async fn install(
     Ok(())
 }
 
-async fn install_path(
+#[allow(clippy::too_many_arguments)]
+pub async fn install_path(
     version: &MinecraftVersion,
     loader_type: &LoaderType,
     loader_version: &LoaderVersion,
-    location: &PathBuf,
+    intermediary_gen: IntermediaryGen,
+    location: &Path,
     install_server: bool,
+    mods: ModSelection,
+    resolver: &MetadataResolver,
+    progress: &Progress,
+    plan: &mut Plan,
 ) -> Result<(), InstallerError> {
-    if !location.exists() {
-        std::fs::create_dir_all(&location)?;
-    }
-    let location = location.canonicalize()?;
+    let location = plan.create_dir(location)?;
+    plan.begin(&location)?;
 
     info!(
         "Installing server for {} using {} Loader {} to {}",
@@ -65,140 +99,161 @@ async fn install_path(
         location.to_str().unwrap_or("<not representable>")
     );
 
-    let clear_paths = [location.join(".fabric"), location.join(".quilt")];
+    // Caches of every loader are cleared, a previous install may have used another one
+    let clear_paths = crate::config::get()
+        .loaders
+        .all()
+        .iter()
+        .flat_map(|loader| &loader.definition().cleanup_paths)
+        .map(|path| location.join(path));
     for path in clear_paths {
-        if path.exists() {
-            std::fs::remove_dir_all(&path)?;
-        }
+        plan.remove(&path)?;
     }
 
-    let launch_json_str = crate::net::meta::fetch_launch_json(
-        crate::net::GameSide::Server,
+    let launch_json = crate::net::meta::fetch_launch_json(
+        GameSide::Server,
         version,
         loader_type,
         loader_version,
+        intermediary_gen,
+        resolver,
     )
     .await?;
 
-    info!("Installing libraries");
-
-    let launch_json = serde_json::from_str::<Value>(&launch_json_str)?;
+    let mods = mods.resolve(version, loader_type).await?;
 
-    if !launch_json.is_object() {
-        return Err(InstallerError(
-            "Cannot create server installation due to server endpoint returning wrong type."
-                .to_owned(),
-        ));
+    let mut receipt = Receipt::new(
+        &version.id,
+        loader_type,
+        loader_version,
+        receipt::intermediary_version(&launch_json, intermediary_gen),
+        intermediary_gen,
+    );
+    receipt.add_source(
+        crate::net::meta::launch_json_url(
+            &GameSide::Server,
+            version,
+            loader_type,
+            &loader_version.version,
+            intermediary_gen,
+            resolver,
+        )
+        .await?,
+    );
+    for library in &launch_json.libraries {
+        receipt.add_source(library_url(&library.url, &library.name));
     }
 
-    let mut main_class = "";
-    let mut launch_main_class: String;
+    progress.phase("Installing libraries");
 
-    match loader_type {
-        LoaderType::Fabric => {
-            main_class = &launch_json["mainClass"]
-                .as_str()
-                .ok_or(InstallerError("Could not find main class entry".to_owned()))?;
-            launch_main_class = "net.fabricmc.loader.launch.server.FabricServerLauncher".to_owned();
-        }
-        LoaderType::Quilt => {
-            launch_main_class = launch_json["launcherMainClass"]
-                .as_str()
-                .ok_or(InstallerError("Could not find main class entry".to_owned()))?
-                .to_owned();
-        }
-    }
+    let main_class = &launch_json.main_class;
+    let mut launch_main_class = match &loader_type.definition().server.launcher_main_class {
+        Some(class) => class.clone(),
+        None => launch_json
+            .launcher_main_class
+            .clone()
+            .ok_or(InstallerError::parse("Could not find main class entry"))?,
+    };
 
-    let libraries = launch_json["libraries"]
-        .as_array()
-        .ok_or(InstallerError("No libraries were specified".to_owned()))?;
+    let libraries = &launch_json.libraries;
 
     let mut library_files = JoinSet::new();
+    progress.totals(libraries.len(), None);
 
     let mut fabric_loader_artifact = None;
+    let mut downloaded_library_files = Vec::new();
     for library in libraries {
-        let name = library["name"]
-            .as_str()
-            .ok_or(InstallerError("Library had no name!".to_owned()))?
-            .to_owned();
-        let url = library["url"]
-            .as_str()
-            .ok_or(InstallerError("Library had no url!".to_owned()))?
-            .to_owned();
+        let name = library.name.clone();
+        let url = library.url.clone();
 
         if name.matches("net\\.fabricmc:fabric-loader:.*").count() > 0 {
             fabric_loader_artifact = Some(name.clone());
         }
-        let dir = location.join("libraries");
-        library_files.spawn(async move { download_library(&dir, name, url).await });
+        let file = location.join("libraries").join(split_artifact(&name));
+        if let Some(target) = plan.download(&library_url(&url, &name), &file)? {
+            let progress = progress.clone();
+            library_files
+                .spawn(async move { download_library(&target, name, url, &progress).await });
+        }
+        downloaded_library_files.push(file);
     }
 
-    let mut downloaded_library_files = Vec::new();
     while let Some(done) = library_files.join_next().await {
-        match done {
-            Ok(res) => match res {
-                Ok(file) => downloaded_library_files.push(file),
-                Err(e) => {
-                    return Err(InstallerError(
-                        "Failed to download libraries: ".to_owned() + &e.0,
-                    ));
-                }
-            },
-            Err(e) => {
-                return Err(InstallerError(
-                    "Failed to download libraries: ".to_owned() + &e.to_string(),
-                ));
-            }
-        }
+        done??;
     }
 
     info!("Downloaded {} libraries!", downloaded_library_files.len());
 
-    if let Some(loader) = fabric_loader_artifact {
+    // The loader jar is only there to read once it has been downloaded
+    if let Some(loader) = fabric_loader_artifact
+        && !plan.is_dry_run()
+    {
         let lib = location.join("libraries").join(split_artifact(&loader));
-        launch_main_class = read_jar_manifest_attribute(&lib, "Main-Class")?;
-    }
-
-    if !location.exists() {
-        std::fs::create_dir_all(&location)?;
+        launch_main_class = read_jar_manifest_attribute(&plan.source(&lib), "Main-Class")?;
     }
 
-    create_launch_jar(
+    progress.phase("Creating launch jar");
+    let launch_jar = create_launch_jar(
         version,
         &location,
         loader_type,
+        intermediary_gen,
         main_class,
         &launch_main_class,
         &downloaded_library_files,
+        plan,
     )
     .await?;
+    let mut written = downloaded_library_files;
+    written.push(launch_jar);
 
     if install_server {
-        info!("Downloading server jar");
+        progress.phase("Downloading server jar");
         let url = version
-            .get_jar_download_url(&crate::net::GameSide::Server)
+            .get_jar_download_url(&GameSide::Server, resolver)
             .await?;
-        crate::net::download_file(&url.url, &location.join("server.jar")).await?;
+        let server_jar = location.join("server.jar");
+        if let Some(target) = plan.download(&url.url, &server_jar)? {
+            progress.totals(1, Some(url.size));
+            crate::net::download_file(&url.url, &target, &url.integrity(), progress).await?;
+        }
+        receipt.add_source(url.url);
+        written.push(server_jar);
+    }
+
+    for source in mods.sources(&GameSide::Server) {
+        receipt.add_source(source);
+    }
+    written.extend(
+        mods.install(&location, &GameSide::Server, progress, plan)
+            .await?,
+    );
+    if !plan.is_dry_run() {
+        for file in &written {
+            receipt.add_staged_file(&location, file, &plan.source(file))?;
+        }
     }
+    plan.write(&location.join(receipt::RECEIPT_FILE), receipt.to_vec()?)?;
+    plan.commit()?;
 
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn create_launch_jar(
     version: &MinecraftVersion,
     install_location: &PathBuf,
     loader_type: &LoaderType,
+    intermediary_gen: IntermediaryGen,
     main_class: &str,
     launch_main_class: &str,
     library_files: &Vec<PathBuf>,
-) -> Result<(), InstallerError> {
-    let jar_out = install_location.join(loader_type.get_name().to_owned() + "-server-launch.jar");
-    if jar_out.exists() {
-        std::fs::remove_file(&jar_out)?;
-    }
+    plan: &mut Plan,
+) -> Result<PathBuf, InstallerError> {
+    let jar_out =
+        install_location.join(loader_type.get_name().to_owned() + installs::LAUNCH_JAR_SUFFIX);
 
-    let file = std::fs::File::create(jar_out)?;
-    let mut zip = ZipWriter::new(file);
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
 
     zip.start_file("META-INF/MANIFEST.MF", SimpleFileOptions::default())?;
 
@@ -223,20 +278,22 @@ async fn create_launch_jar(
         "{}\r",
         wrap_manifest_line(&format!("Minecraft-Version: {}\r", version.id))
     )?;
+    writeln!(
+        manifest,
+        "{}\r",
+        wrap_manifest_line(&format!("Intermediary-Generation: {}", intermediary_gen.0))
+    )?;
     zip.write_all(&manifest)?;
     zip.add_directory("META-INF", SimpleFileOptions::default())?;
 
-    if loader_type == &LoaderType::Fabric {
-        zip.start_file(
-            "fabric-server-launch.properties",
-            SimpleFileOptions::default(),
-        )?;
+    if let Some(properties_file) = &loader_type.definition().server.properties_file {
+        zip.start_file(properties_file, SimpleFileOptions::default())?;
         zip.write_all(("launch.mainClass=".to_owned() + main_class + "\n").as_bytes())?;
     }
 
-    zip.finish()?;
+    plan.write(&jar_out, zip.finish()?.into_inner())?;
 
-    Ok(())
+    Ok(jar_out)
 }
 
 fn wrap_manifest_line(line: &str) -> String {
@@ -254,65 +311,111 @@ fn wrap_manifest_line(line: &str) -> String {
     res
 }
 
-fn read_jar_manifest_attribute(
-    jar_file: &PathBuf,
-    attribute: &str,
-) -> Result<String, InstallerError> {
-    let attribute = &(attribute.to_owned() + ": ");
+fn read_jar_manifest_attribute(jar_file: &Path, attribute: &str) -> Result<String, InstallerError> {
+    read_jar_manifest(jar_file)?
+        .remove(attribute)
+        .ok_or_else(|| {
+            InstallerError::NotFound(
+                "Couldn't find '".to_owned() + attribute + "' attribute in jar manifest!",
+            )
+        })
+}
+
+/// Reads the main attributes of a jar manifest, joining wrapped lines.
+pub fn read_jar_manifest(jar_file: &Path) -> Result<HashMap<String, String>, InstallerError> {
     let file = std::fs::File::open(jar_file)?;
     let mut zip = ZipArchive::new(file)?;
 
     let mut manifest = zip.by_name("META-INF/MANIFEST.MF")?;
     let mf_str = std::io::read_to_string(&mut manifest)?;
-    let main_class_line = mf_str.split("\n").find(|line| line.starts_with(attribute));
-    if let Some(line) = main_class_line {
-        let class = line.strip_prefix(attribute);
-        if let Some(name) = class {
-            return Ok(name.to_owned());
+    let mut lines: Vec<String> = Vec::new();
+    for line in mf_str.split('\n').map(|line| line.trim_end_matches('\r')) {
+        // The main section ends at the first empty line
+        if line.is_empty() {
+            break;
+        }
+        match (line.strip_prefix(' '), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(line.to_owned()),
         }
     }
-
-    Err(InstallerError(
-        "Couldn't find '".to_owned() + attribute + "' attribute in jar manifest!",
-    ))
+    Ok(lines
+        .into_iter()
+        .filter_map(|line| {
+            line.split_once(": ")
+                .map(|(name, value)| (name.to_owned(), value.trim_end_matches('\r').to_owned()))
+        })
+        .collect())
 }
 
 async fn download_library(
-    libraries_dir: &PathBuf,
+    file: &Path,
     name: String,
     url: String,
-) -> Result<PathBuf, InstallerError> {
-    let split_artifact = split_artifact(&name);
-    let file = libraries_dir.join(&split_artifact);
-    let raw_url = url.to_owned() + &split_artifact;
-    crate::net::download_file(&raw_url, &file).await?;
+    progress: &Progress,
+) -> Result<(), InstallerError> {
+    let raw_url = library_url(&url, &name);
+    let integrity = fetch_sha1(&raw_url).await?;
+    crate::net::download_file(&raw_url, file, &integrity, progress).await
+}
+
+/// The checksum a maven repository publishes next to a file. Files without
+/// one are not downloaded, as they could not be verified.
+pub async fn fetch_sha1(url: &str) -> Result<Integrity, InstallerError> {
+    let sha1 = crate::net::fetch_text(&(url.to_owned() + ".sha1"))
+        .await
+        .map_err(|e| {
+            InstallerError::Integrity(format!(
+                "Could not fetch the checksum of {}: {}",
+                url,
+                e.report()
+            ))
+        })?;
+    sha1.split_whitespace()
+        .next()
+        .map(Integrity::sha1)
+        .ok_or_else(|| InstallerError::Integrity(format!("The checksum of {} is empty", url)))
+}
 
-    Ok(file)
+/// The url of a library in the maven repository hosting it.
+pub fn library_url(repository: &str, name: &str) -> String {
+    repository.trim_end_matches('/').to_owned() + "/" + &split_artifact(name)
 }
 
-fn split_artifact(artifact: &str) -> String {
-    let parts = artifact.splitn(3, ":").collect::<Vec<&str>>();
-    let group = parts.get(0).unwrap().replace(".", "/");
+/// The path of a library below the `libraries` folder.
+pub fn split_artifact(artifact: &str) -> String {
+    let parts = artifact.splitn(4, ":").collect::<Vec<&str>>();
+    let group = parts.first().unwrap().replace(".", "/");
     let name = parts.get(1).unwrap();
     let version = parts.get(2).unwrap();
+    let classifier = parts
+        .get(3)
+        .map(|classifier| "-".to_owned() + classifier)
+        .unwrap_or_default();
 
-    group + "/" + name + "/" + version + "/" + name + "-" + version + ".jar"
+    group + "/" + name + "/" + version + "/" + name + "-" + version + &classifier + ".jar"
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn install_and_run<I, S>(
     version: MinecraftVersion,
     loader_type: LoaderType,
     loader_version: LoaderVersion,
+    intermediary_gen: IntermediaryGen,
     location: PathBuf,
     java: Option<&PathBuf>,
     args: Option<I>,
+    mods: ModSelection,
+    resolver: &MetadataResolver,
+    progress: Progress,
 ) -> Result<(), InstallerError>
 where
     I: IntoIterator<Item = S>,
     S: AsRef<OsStr>,
 {
-    let launch_jar = location.join(loader_type.get_name().to_owned() + "-server-launch.jar");
-    let mut needs_install = false;
+    let launch_jar = location.join(loader_type.get_name().to_owned() + installs::LAUNCH_JAR_SUFFIX);
+    // Mods are only installed along with the server
+    let mut needs_install = !mods.is_empty();
     if !launch_jar.exists() {
         needs_install = true;
     }
@@ -323,16 +426,33 @@ where
             .unwrap_or(true);
     }
 
-    if needs_install {
-        install_path(&version, &loader_type, &loader_version, &location, true).await?;
+    if !needs_install {
+        // Launch jars from before generations could be selected don't record one
+        needs_install = read_jar_manifest_attribute(&launch_jar, "Intermediary-Generation")
+            .map(|v| v != intermediary_gen.0.to_string())
+            .unwrap_or(intermediary_gen != IntermediaryGen::default());
     }
 
-    let mut java_binary = "java".to_owned();
-    if let Some(arg) = java {
-        if let Some(path) = arg.to_str() {
-            java_binary = path.to_owned();
-        }
+    if needs_install {
+        install_path(
+            &version,
+            &loader_type,
+            &loader_version,
+            intermediary_gen,
+            &location,
+            true,
+            mods,
+            resolver,
+            &progress,
+            &mut Plan::default(),
+        )
+        .await?;
     }
+
+    let java_binary = match java {
+        Some(java) => java.clone(),
+        None => select_java(&version, &location, resolver, &progress).await?,
+    };
     let jar = launch_jar.canonicalize()?;
 
     let mut cmd = Command::new(java_binary);
@@ -348,3 +468,19 @@ where
 
     Ok(())
 }
+
+/// Picks a runtime suitable for the game version.
+async fn select_java(
+    version: &MinecraftVersion,
+    location: &Path,
+    resolver: &MetadataResolver,
+    progress: &Progress,
+) -> Result<PathBuf, InstallerError> {
+    let vanilla_json = manifest::fetch_launch_json(version, resolver).await?;
+    let required = java::required_major(vanilla_json.java_version.as_ref());
+    let runtimes_dir = crate::config::get()
+        .runtimes_dir
+        .clone()
+        .unwrap_or_else(|| location.join(RUNTIMES_DIR));
+    Ok(java::select_binary(&runtimes_dir, required, progress).await)
+}
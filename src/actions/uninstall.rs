@@ -0,0 +1,372 @@
+//! Removal of the files installations created. Worlds, configs, mods and
+//! anything else not written by the installer is left in place.
+
+use std::{
+    collections::{BTreeSet, HashSet},
+    path::{Component, Path, PathBuf},
+};
+
+use log::{info, warn};
+use serde_json::Value;
+
+use crate::{
+    actions::{
+        installs::{self, InstalledVersions},
+        launcher_profiles::{self, LauncherProfiles},
+        plan::Plan,
+        receipt::{self, Receipt},
+    },
+    errors::InstallerError,
+    net::{GameSide, loaders::LoaderType, resolver::MetadataResolver},
+    progress::Progress,
+};
+
+const INSTANCE_FILES: &[&str] = &[
+    "instance.cfg",
+    "ornithe.png",
+    "mmc-pack.json",
+    receipt::RECEIPT_FILE,
+];
+
+/// Removes the Ornithe profiles of a game directory, together with their
+/// vanilla profiles and launcher profiles. Without a version or loader, every
+/// Ornithe profile is removed.
+pub async fn uninstall_client(
+    location: PathBuf,
+    minecraft_version: Option<String>,
+    loader_type: Option<LoaderType>,
+    progress: Progress,
+) -> Result<(), InstallerError> {
+    progress.phase("Finding installations..");
+    let (removed, kept): (Vec<_>, Vec<_>) = installs::find_profiles(&location)?
+        .into_iter()
+        .partition(|profile| {
+            selected(
+                &profile.versions,
+                minecraft_version.as_deref(),
+                loader_type.as_ref(),
+            )
+        });
+    if removed.is_empty() {
+        return Err(not_found(&location));
+    }
+
+    progress.phase("Removing files..");
+    let mut launcher_profiles = HashSet::new();
+    for profile in &removed {
+        if let Some(receipt) = Receipt::find(&profile.dir) {
+            launcher_profiles.extend(receipt.launcher_profiles);
+        }
+        remove(&profile.dir)?;
+    }
+    // Vanilla profiles are shared by the Ornithe profiles of a game version
+    let minecraft_versions = removed
+        .iter()
+        .map(|profile| profile.versions.minecraft.as_str())
+        .collect::<BTreeSet<_>>();
+    for minecraft in minecraft_versions {
+        if !kept
+            .iter()
+            .any(|profile| profile.versions.minecraft == minecraft)
+        {
+            remove(
+                &location
+                    .join("versions")
+                    .join(installs::vanilla_profile_name(minecraft)),
+            )?;
+        }
+    }
+
+    let names = removed
+        .iter()
+        .map(|profile| profile.name.as_str())
+        .collect::<HashSet<_>>();
+    remove_launcher_profiles(&location, &names, &launcher_profiles)?;
+
+    info!(
+        "Uninstalled {} Ornithe profile(s) from {}",
+        removed.len(),
+        location.display()
+    );
+    Ok(())
+}
+
+/// Removes the launcher profiles the installation added that still start one
+/// of the given versions. Other profiles starting them were only pointed at
+/// the version, they are kept and fall back to the launcher's default version.
+fn remove_launcher_profiles(
+    game_dir: &Path,
+    names: &HashSet<&str>,
+    keys: &HashSet<String>,
+) -> Result<(), InstallerError> {
+    for path in launcher_profiles::existing_files(game_dir) {
+        let mut launcher_profiles = LauncherProfiles::load(&path)?;
+        let profiles = launcher_profiles.profiles();
+        let mut changed = false;
+        profiles.retain(|key, profile| {
+            let starts_removed = profile
+                .get("lastVersionId")
+                .and_then(Value::as_str)
+                .is_some_and(|id| names.contains(id));
+            if !starts_removed {
+                return true;
+            }
+            changed = true;
+            if keys.contains(key) {
+                info!("Removing launcher profile {}", key);
+                return false;
+            }
+            info!("Resetting the version of launcher profile {}", key);
+            if let Some(profile) = profile.as_object_mut() {
+                profile.remove("lastVersionId");
+            }
+            true
+        });
+        if changed {
+            launcher_profiles.save(&mut Plan::default())?;
+        }
+    }
+    Ok(())
+}
+
+/// Removes the generated files of MultiMC/Prism instances. The location is
+/// either an instance or the directory instances were generated in, in which
+/// case every `Ornithe-*` instance matching the version and loader is handled.
+/// The game directories of the instances are kept.
+pub async fn uninstall_mmc(
+    location: PathBuf,
+    minecraft_version: Option<String>,
+    loader_type: Option<LoaderType>,
+    resolver: &MetadataResolver,
+    progress: Progress,
+) -> Result<(), InstallerError> {
+    progress.phase("Finding installations..");
+    let mut instances = Vec::new();
+    for instance in installs::find_instances(&location)? {
+        match installs::read_instance(&instance) {
+            Ok(versions)
+                if selected(
+                    &versions,
+                    minecraft_version.as_deref(),
+                    loader_type.as_ref(),
+                ) =>
+            {
+                instances.push((instance, versions))
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Skipping {}: {}", instance.display(), e.report()),
+        }
+    }
+    if instances.is_empty() {
+        return Err(not_found(&location));
+    }
+
+    progress.phase("Removing files..");
+    for (instance, versions) in &instances {
+        let pack = installs::read_pack(instance)?;
+        let patches = instance.join("patches");
+        let library_components = installs::library_components(instance, versions, resolver).await?;
+        let generated_patches = pack
+            .components
+            .iter()
+            .filter(|component| {
+                installs::INSTANCE_PATCHES.contains(&component.uid.as_str())
+                    || library_components.contains(&component.uid)
+            })
+            .map(|component| patches.join(component.uid.clone() + ".json"));
+        for patch in generated_patches {
+            remove(&patch)?;
+        }
+        // Everything else the installation wrote, except the game directory
+        if let Some(receipt) = Receipt::find(instance) {
+            let game_dir = instance.join(".minecraft");
+            for file in receipt.file_paths(instance) {
+                if !file.starts_with(&game_dir) {
+                    remove(&file)?;
+                }
+            }
+        }
+        remove_if_empty(&patches)?;
+        for file in INSTANCE_FILES {
+            remove(&instance.join(file))?;
+        }
+        if !remove_if_empty(instance)? {
+            info!(
+                "Kept {} as it contains the game directory",
+                instance.display()
+            );
+        }
+    }
+
+    info!("Uninstalled {} instance(s)", instances.len());
+    Ok(())
+}
+
+/// Removes the launch jars of a server directory and the libraries, caches and
+/// runtimes belonging to them. The server jar is only removed if it is the
+/// official one.
+pub async fn uninstall_server(
+    location: PathBuf,
+    loader_type: Option<LoaderType>,
+    resolver: &MetadataResolver,
+    progress: Progress,
+) -> Result<(), InstallerError> {
+    progress.phase("Finding installations..");
+    let (removed, kept): (Vec<_>, Vec<_>) = installs::find_servers(&location)
+        .into_iter()
+        .partition(|server| selected(&server.versions, None, loader_type.as_ref()));
+    if removed.is_empty() {
+        return Err(not_found(&location));
+    }
+
+    progress.phase("Removing files..");
+    let libraries_dir = location.join("libraries");
+    let kept_libraries = kept
+        .iter()
+        .flat_map(|server| &server.libraries)
+        .collect::<HashSet<_>>();
+    for server in &removed {
+        for library in &server.libraries {
+            // Only what the installer downloaded, whatever the class path says
+            let downloaded = library.starts_with(&libraries_dir)
+                && !library
+                    .components()
+                    .any(|component| component == Component::ParentDir);
+            if downloaded && !kept_libraries.contains(library) {
+                remove(library)?;
+            }
+        }
+        remove(&server.launch_jar)?;
+    }
+    prune_empty_dirs(&libraries_dir)?;
+    let receipt_removed = Receipt::find(&location).is_none_or(|receipt| {
+        removed
+            .iter()
+            .any(|server| server.versions.loader.get_name() == receipt.loader_type)
+    });
+    if kept.is_empty() || receipt_removed {
+        remove(&location.join(receipt::RECEIPT_FILE))?;
+    }
+
+    if kept.is_empty() {
+        for loader in crate::config::get().loaders.all() {
+            for path in &loader.definition().cleanup_paths {
+                remove(&location.join(path))?;
+            }
+        }
+        let runtimes = location.join(super::server::RUNTIMES_DIR);
+        if runtimes.is_dir() {
+            for entry in std::fs::read_dir(&runtimes)? {
+                let path = entry?.path();
+                if path
+                    .file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with("java-"))
+                {
+                    remove(&path)?;
+                }
+            }
+            remove_if_empty(&runtimes)?;
+        }
+        remove_server_jar(&location, &removed[0].versions.minecraft, resolver).await;
+    }
+
+    info!(
+        "Uninstalled {} server launch jar(s) from {}",
+        removed.len(),
+        location.display()
+    );
+    Ok(())
+}
+
+/// Removes the server jar if it is the one downloaded for the game version,
+/// a jar the user placed there is kept.
+async fn remove_server_jar(location: &Path, minecraft: &str, resolver: &MetadataResolver) {
+    let server_jar = location.join("server.jar");
+    if !server_jar.is_file() {
+        return;
+    }
+    let official = async {
+        let manifest = resolver.versions().await?;
+        let version = manifest
+            .versions
+            .iter()
+            .find(|version| version.id == minecraft)
+            .ok_or_else(|| {
+                InstallerError::NotFound(format!("Unknown Minecraft version {}", minecraft))
+            })?;
+        let download = version
+            .get_jar_download_url(&GameSide::Server, resolver)
+            .await?;
+        Ok::<_, InstallerError>(download.integrity().check(&server_jar)?.is_none())
+    }
+    .await;
+    match official {
+        Ok(true) => {
+            if let Err(e) = remove(&server_jar) {
+                warn!("Failed to remove {}: {}", server_jar.display(), e.report());
+            }
+        }
+        Ok(false) => info!(
+            "Kept {} as it is not the official server jar",
+            server_jar.display()
+        ),
+        Err(e) => warn!(
+            "Kept {} as it could not be checked: {}",
+            server_jar.display(),
+            e.report()
+        ),
+    }
+}
+
+fn selected(
+    versions: &InstalledVersions,
+    minecraft_version: Option<&str>,
+    loader_type: Option<&LoaderType>,
+) -> bool {
+    minecraft_version.is_none_or(|minecraft| versions.minecraft == minecraft)
+        && loader_type.is_none_or(|loader| versions.loader == *loader)
+}
+
+fn not_found(location: &Path) -> InstallerError {
+    InstallerError::NotFound(format!(
+        "Found no matching Ornithe installation in {}",
+        location.display()
+    ))
+}
+
+fn remove(path: &Path) -> Result<(), InstallerError> {
+    if path.is_dir() {
+        info!("Removing {}", path.display());
+        std::fs::remove_dir_all(path)?;
+    } else if path.exists() {
+        info!("Removing {}", path.display());
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// Removes a directory if it exists and is empty, returning whether it is gone.
+fn remove_if_empty(dir: &Path) -> Result<bool, InstallerError> {
+    if !dir.is_dir() {
+        return Ok(true);
+    }
+    if std::fs::read_dir(dir)?.next().is_some() {
+        return Ok(false);
+    }
+    std::fs::remove_dir(dir)?;
+    Ok(true)
+}
+
+/// Removes the empty directories below and including the given one.
+fn prune_empty_dirs(dir: &Path) -> Result<bool, InstallerError> {
+    if !dir.is_dir() {
+        return Ok(true);
+    }
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            prune_empty_dirs(&entry.path())?;
+        }
+    }
+    remove_if_empty(dir)
+}
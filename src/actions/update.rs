@@ -0,0 +1,551 @@
+//! In-place updates of existing installations to another loader version.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Component, Path, PathBuf},
+};
+
+use log::{info, warn};
+use serde_json::Value;
+
+use crate::{
+    actions::{
+        installs::{self, InstalledVersions},
+        launcher_profiles::{self, LauncherProfiles},
+        mods::ModSelection,
+        plan::Plan,
+        receipt::{self, Receipt},
+    },
+    errors::InstallerError,
+    net::{
+        GameSide,
+        loaders::LoaderType,
+        manifest::MinecraftVersion,
+        meta::{self, LoaderVersion},
+        resolver::MetadataResolver,
+    },
+    progress::Progress,
+};
+
+/// Which installations to update and the loader version to update them to.
+#[derive(Clone, Debug)]
+pub struct UpdateTarget {
+    pub minecraft_version: Option<String>,
+    pub loader_type: Option<LoaderType>,
+    /// A loader version, or `latest`.
+    pub loader_version: String,
+}
+
+impl UpdateTarget {
+    fn selects(&self, versions: &InstalledVersions) -> bool {
+        self.minecraft_version
+            .as_ref()
+            .is_none_or(|minecraft| versions.minecraft == *minecraft)
+            && self
+                .loader_type
+                .as_ref()
+                .is_none_or(|loader| versions.loader == *loader)
+    }
+
+    /// The version to update an installation to, or none if it is up to date.
+    async fn new_version(
+        &self,
+        versions: &InstalledVersions,
+        resolver: &MetadataResolver,
+    ) -> Result<Option<LoaderVersion>, InstallerError> {
+        let available = resolver.loader_versions().await?;
+        let available = available.get(&versions.loader).ok_or_else(|| {
+            InstallerError::NotFound(format!(
+                "No versions available for {} Loader",
+                versions.loader.get_localized_name()
+            ))
+        })?;
+        let version = match self.loader_version.as_str() {
+            "latest" => available.first(),
+            target => available.iter().find(|version| version.version == target),
+        }
+        .ok_or_else(|| {
+            InstallerError::NotFound(format!(
+                "Could not find {} Loader version {}",
+                versions.loader.get_localized_name(),
+                self.loader_version
+            ))
+        })?;
+        if version.version == versions.loader_version {
+            info!(
+                "{} Loader {} for Minecraft {} is up to date",
+                versions.loader.get_localized_name(),
+                versions.loader_version,
+                versions.minecraft
+            );
+            return Ok(None);
+        }
+        Ok(Some(version.clone()))
+    }
+}
+
+/// Replaces the Ornithe profiles of a game directory with ones for the new
+/// loader version, and points the launcher profiles using them to the new ones.
+pub async fn update_client(
+    location: PathBuf,
+    target: UpdateTarget,
+    resolver: &MetadataResolver,
+    progress: Progress,
+) -> Result<(), InstallerError> {
+    progress.phase("Finding installations..");
+    let profiles = installs::find_profiles(&location)?
+        .into_iter()
+        .filter(|profile| target.selects(&profile.versions))
+        .collect::<Vec<_>>();
+    if profiles.is_empty() {
+        return Err(not_found(&location));
+    }
+
+    let versions_dir = location.join("versions");
+    let mut renamed = HashMap::new();
+    let mut old_libraries = Vec::new();
+    let mut plan = Plan::default();
+    plan.begin(&location)?;
+    for profile in &profiles {
+        let Some(loader_version) = target.new_version(&profile.versions, resolver).await? else {
+            continue;
+        };
+        progress.phase(format!("Updating {}..", profile.name));
+        let version = find_minecraft_version(&profile.versions.minecraft, resolver).await?;
+        let launch_json = meta::fetch_launch_json(
+            GameSide::Client,
+            &version,
+            &profile.versions.loader,
+            &loader_version,
+            profile.versions.intermediary_gen,
+            resolver,
+        )
+        .await?;
+
+        let name = installs::profile_name(
+            &profile.versions.loader,
+            &loader_version.version,
+            &version.id,
+        );
+        let dir = versions_dir.join(&name);
+        plan.remove(&dir)?;
+        let mut receipt = Receipt::new(
+            &version.id,
+            &profile.versions.loader,
+            &loader_version,
+            receipt::intermediary_version(&launch_json, profile.versions.intermediary_gen),
+            profile.versions.intermediary_gen,
+        );
+        receipt.add_source(crate::config::get().endpoints.version_json(&version.id));
+        let old_source = launch_json_urls(
+            &GameSide::Client,
+            &version,
+            &profile.versions,
+            &loader_version,
+            &mut receipt,
+            resolver,
+        )
+        .await?;
+        for file in super::client::write_profile(&dir, &name, &launch_json, true, &mut plan)? {
+            receipt.add_staged_file(&location, &file, &plan.source(&file))?;
+        }
+
+        let old_json = profile.dir.join(profile.name.clone() + ".json");
+        if let Ok(json) = std::fs::read_to_string(old_json)
+            && let Ok(json) = serde_json::from_str::<Value>(&json)
+        {
+            old_libraries.extend(library_paths(&json));
+        }
+        if let Some(mut previous) = Receipt::find(&profile.dir) {
+            // The old profile is only removed on commit, its files are not carried over
+            let old_dir = profile.dir.strip_prefix(&location).unwrap_or(&profile.dir);
+            previous
+                .files
+                .retain(|file| !Path::new(&file.path).starts_with(old_dir));
+            receipt.carry_over(previous, &location, |source| source == old_source);
+        }
+        plan.remove(&profile.dir)?;
+        plan.write(&dir.join(receipt::RECEIPT_FILE), receipt.to_vec()?)?;
+        info!("Updated {} to {}", profile.name, name);
+        renamed.insert(profile.name.clone(), name);
+    }
+
+    if renamed.is_empty() {
+        plan.commit()?;
+        return Ok(());
+    }
+    rename_launcher_profiles(&location, &renamed, &mut plan)?;
+    plan.commit()?;
+    remove_stale_client_libraries(&location, &old_libraries)?;
+    info!("Updated {} Ornithe profile(s)", renamed.len());
+    Ok(())
+}
+
+/// Points the launcher profiles starting an old version to its replacement.
+fn rename_launcher_profiles(
+    game_dir: &Path,
+    renamed: &HashMap<String, String>,
+    plan: &mut Plan,
+) -> Result<(), InstallerError> {
+    for path in launcher_profiles::existing_files(game_dir) {
+        let mut launcher_profiles = LauncherProfiles::load(&path)?;
+        let mut changed = false;
+        for (key, profile) in launcher_profiles.profiles().iter_mut() {
+            let Some(last_version_id) = profile.get_mut("lastVersionId") else {
+                continue;
+            };
+            if let Some(name) = last_version_id.as_str().and_then(|id| renamed.get(id)) {
+                info!("Updating launcher profile {}", key);
+                *last_version_id = Value::String(name.clone());
+                changed = true;
+            }
+        }
+        if changed {
+            launcher_profiles.save(plan)?;
+        }
+    }
+    Ok(())
+}
+
+/// Removes the libraries the launcher downloaded for the replaced profiles that
+/// no remaining version uses.
+fn remove_stale_client_libraries(
+    game_dir: &Path,
+    old_libraries: &[String],
+) -> Result<(), InstallerError> {
+    let versions_dir = game_dir.join("versions");
+    let mut referenced = HashSet::new();
+    for entry in std::fs::read_dir(&versions_dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let json = entry.path().join(name + ".json");
+        if let Ok(json) = std::fs::read_to_string(json)
+            && let Ok(json) = serde_json::from_str::<Value>(&json)
+        {
+            referenced.extend(library_paths(&json));
+        }
+    }
+
+    let libraries_dir = game_dir.join("libraries");
+    let mut removed = 0;
+    for library in old_libraries {
+        if referenced.contains(library) || !is_relative(library) {
+            continue;
+        }
+        let file = libraries_dir.join(library);
+        if file.is_file() {
+            std::fs::remove_file(&file)?;
+            removed += 1;
+            if let Some(parent) = file.parent() {
+                prune_empty_parents(parent, &libraries_dir)?;
+            }
+        }
+    }
+    if removed > 0 {
+        info!("Removed {} stale libraries", removed);
+    }
+    Ok(())
+}
+
+/// The paths below `libraries/` of the libraries of a version json.
+fn library_paths(json: &Value) -> Vec<String> {
+    let Some(libraries) = json.get("libraries").and_then(Value::as_array) else {
+        return Vec::new();
+    };
+    libraries
+        .iter()
+        .filter_map(|library| {
+            let path = library
+                .pointer("/downloads/artifact/path")
+                .and_then(Value::as_str);
+            match path {
+                Some(path) => Some(path.to_owned()),
+                None => library
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .filter(|name| name.matches(':').count() >= 2)
+                    .map(super::server::split_artifact),
+            }
+        })
+        .collect()
+}
+
+/// Updates the loader component of generated MultiMC/Prism instances and
+/// replaces the patches of the loader's libraries.
+pub async fn update_mmc(
+    location: PathBuf,
+    target: UpdateTarget,
+    resolver: &MetadataResolver,
+    progress: Progress,
+) -> Result<(), InstallerError> {
+    progress.phase("Finding installations..");
+    let mut instances = Vec::new();
+    for instance in installs::find_instances(&location)? {
+        match installs::read_instance(&instance) {
+            Ok(versions) if target.selects(&versions) => instances.push((instance, versions)),
+            Ok(_) => {}
+            Err(e) => warn!("Skipping {}: {}", instance.display(), e.report()),
+        }
+    }
+    if instances.is_empty() {
+        return Err(not_found(&location));
+    }
+
+    let mut updated = 0;
+    for (instance, versions) in &instances {
+        let Some(loader_version) = target.new_version(versions, resolver).await? else {
+            continue;
+        };
+        progress.phase(format!("Updating {}..", instance.display()));
+        let version = find_minecraft_version(&versions.minecraft, resolver).await?;
+        let version_id = version.get_id(&GameSide::Client, resolver).await?;
+        let intermediary_versions = resolver
+            .intermediary_versions(versions.intermediary_gen)
+            .await?;
+        let intermediary_version = intermediary_versions.get(&version_id).ok_or_else(|| {
+            InstallerError::NotFound("Could not find matching intermediary version".to_owned())
+        })?;
+        let libraries = meta::fetch_profile_libraries(
+            intermediary_version,
+            &versions.loader,
+            &loader_version,
+            versions.intermediary_gen,
+            resolver,
+        )
+        .await?;
+
+        let mut receipt = Receipt::new(
+            &version.id,
+            &versions.loader,
+            &loader_version,
+            Some(intermediary_version.version.clone()),
+            versions.intermediary_gen,
+        );
+        receipt.add_source(crate::config::get().endpoints.version_json(&version.id));
+        let old_source = launch_json_urls(
+            &GameSide::Client,
+            &version,
+            versions,
+            &loader_version,
+            &mut receipt,
+            resolver,
+        )
+        .await?;
+
+        let mut pack = installs::read_pack(instance)?;
+        let patches = instance.join("patches");
+        let library_components = installs::library_components(instance, versions, resolver).await?;
+        for uid in &library_components {
+            let patch = patches.join(uid.clone() + ".json");
+            if patch.exists() {
+                std::fs::remove_file(patch)?;
+            }
+        }
+        pack.components
+            .retain(|component| !library_components.contains(&component.uid));
+
+        let loader_uid = versions.loader.get_maven_uid();
+        if let Some(component) = pack
+            .components
+            .iter_mut()
+            .find(|component| component.uid == loader_uid)
+        {
+            component.version = Some(loader_version.version.clone());
+            component.cached_version = Some(loader_version.version.clone());
+        }
+        for library in &libraries {
+            let (patch, component) = super::mmc_pack::library_patch(library)?;
+            std::fs::create_dir_all(&patches)?;
+            let patch_file = patches.join(component.uid.clone() + ".json");
+            std::fs::write(&patch_file, serde_json::to_vec_pretty(&patch)?)?;
+            receipt.add_file(instance, &patch_file)?;
+            pack.components.push(component);
+        }
+        let pack_file = instance.join("mmc-pack.json");
+        std::fs::write(&pack_file, serde_json::to_vec_pretty(&pack)?)?;
+        receipt.add_file(instance, &pack_file)?;
+        if let Some(previous) = Receipt::find(instance) {
+            receipt.carry_over(previous, instance, |source| source == old_source);
+        }
+        receipt.write(instance)?;
+        info!(
+            "Updated {} from {} Loader {} to {}",
+            instance.display(),
+            versions.loader.get_localized_name(),
+            versions.loader_version,
+            loader_version.version
+        );
+        updated += 1;
+    }
+
+    if updated > 0 {
+        info!("Updated {} instance(s)", updated);
+    }
+    Ok(())
+}
+
+/// Installs the libraries of the new loader version into a server directory,
+/// rewrites the class path of its launch jar and removes the old libraries.
+pub async fn update_server(
+    location: PathBuf,
+    target: UpdateTarget,
+    resolver: &MetadataResolver,
+    progress: Progress,
+) -> Result<(), InstallerError> {
+    progress.phase("Finding installations..");
+    let servers = installs::find_servers(&location)
+        .into_iter()
+        .filter(|server| target.selects(&server.versions))
+        .collect::<Vec<_>>();
+    if servers.is_empty() {
+        return Err(not_found(&location));
+    }
+
+    let libraries_dir = location.join("libraries");
+    let mut updated = 0;
+    for server in &servers {
+        let Some(loader_version) = target.new_version(&server.versions, resolver).await? else {
+            continue;
+        };
+        let version = find_minecraft_version(&server.versions.minecraft, resolver).await?;
+        // The receipt of a server directory belongs to the loader installed last
+        let previous = Receipt::find(&location)
+            .filter(|receipt| receipt.loader_type == server.versions.loader.get_name());
+        super::server::install_path(
+            &version,
+            &server.versions.loader,
+            &loader_version,
+            server.versions.intermediary_gen,
+            &location,
+            false,
+            ModSelection::default(),
+            resolver,
+            &progress,
+            &mut Plan::default(),
+        )
+        .await?;
+
+        // Libraries of the other launch jars in the directory are kept as well
+        let in_use = installs::find_servers(&location)
+            .into_iter()
+            .flat_map(|server| server.libraries)
+            .collect::<HashSet<_>>();
+        let mut removed = Vec::new();
+        for library in &server.libraries {
+            let downloaded = library.starts_with(&libraries_dir)
+                && !library
+                    .components()
+                    .any(|component| component == Component::ParentDir);
+            if downloaded && !in_use.contains(library) && library.is_file() {
+                std::fs::remove_file(library)?;
+                removed.push(library.strip_prefix(&libraries_dir)?.to_owned());
+                if let Some(parent) = library.parent() {
+                    prune_empty_parents(parent, &libraries_dir)?;
+                }
+            }
+        }
+
+        if let Some(previous) = previous
+            && let Some(mut receipt) = Receipt::find(&location)
+        {
+            let old_source = meta::launch_json_url(
+                &GameSide::Server,
+                &version,
+                &server.versions.loader,
+                &server.versions.loader_version,
+                server.versions.intermediary_gen,
+                resolver,
+            )
+            .await?;
+            // Libraries are downloaded from <repository>/<path below libraries/>
+            let removed_urls = removed
+                .iter()
+                .map(|path| "/".to_owned() + &path.to_string_lossy().replace('\\', "/"))
+                .collect::<Vec<_>>();
+            receipt.carry_over(previous, &location, |source| {
+                source == old_source || removed_urls.iter().any(|path| source.ends_with(path))
+            });
+            receipt.write(&location)?;
+        }
+        info!(
+            "Updated {} Loader from {} to {}, removed {} stale libraries",
+            server.versions.loader.get_localized_name(),
+            server.versions.loader_version,
+            loader_version.version,
+            removed.len()
+        );
+        updated += 1;
+    }
+
+    if updated > 0 {
+        info!("Updated {} server launch jar(s)", updated);
+    }
+    Ok(())
+}
+
+/// Adds the launch json of the new loader version to the sources of a receipt,
+/// returning the url of the old one.
+async fn launch_json_urls(
+    side: &GameSide,
+    version: &MinecraftVersion,
+    versions: &InstalledVersions,
+    loader_version: &LoaderVersion,
+    receipt: &mut Receipt,
+    resolver: &MetadataResolver,
+) -> Result<String, InstallerError> {
+    let url = |loader_version| {
+        meta::launch_json_url(
+            side,
+            version,
+            &versions.loader,
+            loader_version,
+            versions.intermediary_gen,
+            resolver,
+        )
+    };
+    receipt.add_source(url(&loader_version.version).await?);
+    url(&versions.loader_version).await
+}
+
+async fn find_minecraft_version(
+    id: &str,
+    resolver: &MetadataResolver,
+) -> Result<MinecraftVersion, InstallerError> {
+    resolver
+        .versions()
+        .await?
+        .versions
+        .iter()
+        .find(|version| version.id == id)
+        .cloned()
+        .ok_or_else(|| InstallerError::NotFound(format!("Unknown Minecraft version {}", id)))
+}
+
+fn not_found(location: &Path) -> InstallerError {
+    InstallerError::NotFound(format!(
+        "Found no matching Ornithe installation in {}",
+        location.display()
+    ))
+}
+
+fn is_relative(path: &str) -> bool {
+    Path::new(path)
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+}
+
+/// Removes empty directories from the given one upwards, stopping at the root.
+fn prune_empty_parents(dir: &Path, root: &Path) -> Result<(), InstallerError> {
+    let mut dir = dir;
+    while dir != root && dir.starts_with(root) {
+        if std::fs::read_dir(dir)?.next().is_some() {
+            break;
+        }
+        std::fs::remove_dir(dir)?;
+        match dir.parent() {
+            Some(parent) => dir = parent,
+            None => break,
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,216 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+    time::Duration,
+};
+
+use serde::Deserialize;
+
+use crate::{
+    errors::InstallerError,
+    net::{
+        DownloadSettings,
+        endpoints::Endpoints,
+        loaders::{LoaderDefinition, LoaderRegistry},
+    },
+};
+
+const CONFIG_ENV: &str = "ORNITHE_INSTALLER_CONFIG";
+const META_URL_ENV: &str = "ORNITHE_META_URL";
+const MC_VERSIONS_URL_ENV: &str = "ORNITHE_MC_VERSIONS_URL";
+const MAVEN_URL_ENV: &str = "ORNITHE_MAVEN_URL";
+const MODRINTH_URL_ENV: &str = "ORNITHE_MODRINTH_URL";
+const ADOPTIUM_URL_ENV: &str = "ORNITHE_ADOPTIUM_URL";
+const CACHE_DIR_ENV: &str = "ORNITHE_CACHE_DIR";
+const RUNTIMES_DIR_ENV: &str = "ORNITHE_RUNTIMES_DIR";
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Settings shared by every part of the installer.
+///
+/// Values are taken from (in increasing order of precedence) the built-in
+/// defaults, the config file, environment variables and command line flags.
+pub struct Config {
+    pub endpoints: Endpoints,
+    /// Directory for cached metadata responses, `None` disables the cache.
+    pub cache_dir: Option<PathBuf>,
+    /// Only serve metadata from the cache and never touch the network.
+    pub offline: bool,
+    pub download: DownloadSettings,
+    pub loaders: LoaderRegistry,
+    /// Shared directory for downloaded Java runtimes. If unset, runtimes are
+    /// kept in the directory of the server that needs them.
+    pub runtimes_dir: Option<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            endpoints: Endpoints::default(),
+            cache_dir: crate::net::cache::default_cache_dir(),
+            offline: false,
+            download: DownloadSettings::default(),
+            loaders: LoaderRegistry::default(),
+            runtimes_dir: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct ConfigFile {
+    endpoints: EndpointsFile,
+    cache_dir: Option<PathBuf>,
+    offline: bool,
+    download: DownloadFile,
+    loaders: Vec<LoaderDefinition>,
+    runtimes_dir: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct DownloadFile {
+    connect_timeout_secs: Option<u64>,
+    read_timeout_secs: Option<u64>,
+    retries: Option<u32>,
+    max_concurrent: Option<usize>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct EndpointsFile {
+    meta: Option<String>,
+    mc_versions: Option<String>,
+    maven: Option<String>,
+    modrinth: Option<String>,
+    adoptium: Option<String>,
+    mirrors: BTreeMap<String, String>,
+}
+
+impl Config {
+    /// Loads the configuration from the given file, or from the default
+    /// location if none is given, and applies environment overrides.
+    ///
+    /// A missing file is only an error if its path was given explicitly.
+    pub fn load(path: Option<PathBuf>) -> Result<Config, InstallerError> {
+        let explicit = path.or(std::env::var_os(CONFIG_ENV).map(PathBuf::from));
+        let file = match &explicit {
+            Some(path) => Some(read_config_file(path)?),
+            None => match default_config_file() {
+                Some(path) if path.exists() => Some(read_config_file(&path)?),
+                _ => None,
+            },
+        }
+        .unwrap_or_default();
+
+        let mut config = Config::default();
+
+        let endpoints = file.endpoints;
+        if let Some(meta) = endpoints.meta {
+            config.endpoints.set_meta(meta);
+        }
+        if let Some(mc_versions) = endpoints.mc_versions {
+            config.endpoints.set_mc_versions(mc_versions);
+        }
+        if let Some(maven) = endpoints.maven {
+            config.endpoints.set_maven(maven);
+        }
+        if let Some(modrinth) = endpoints.modrinth {
+            config.endpoints.set_modrinth(modrinth);
+        }
+        if let Some(adoptium) = endpoints.adoptium {
+            config.endpoints.set_adoptium(adoptium);
+        }
+        for (from, to) in endpoints.mirrors {
+            config.endpoints.add_mirror(from, to);
+        }
+        if file.cache_dir.is_some() {
+            config.cache_dir = file.cache_dir;
+        }
+        config.offline = file.offline;
+
+        let download = file.download;
+        if let Some(secs) = download.connect_timeout_secs {
+            config.download.connect_timeout = Duration::from_secs(secs);
+        }
+        if let Some(secs) = download.read_timeout_secs {
+            config.download.read_timeout = Duration::from_secs(secs);
+        }
+        if let Some(retries) = download.retries {
+            config.download.retries = retries;
+        }
+        if let Some(max_concurrent) = download.max_concurrent {
+            config.download.max_concurrent = max_concurrent;
+        }
+
+        for loader in file.loaders {
+            config.loaders.register(loader);
+        }
+        config.runtimes_dir = file.runtimes_dir;
+
+        if let Ok(meta) = std::env::var(META_URL_ENV) {
+            config.endpoints.set_meta(meta);
+        }
+        if let Ok(mc_versions) = std::env::var(MC_VERSIONS_URL_ENV) {
+            config.endpoints.set_mc_versions(mc_versions);
+        }
+        if let Ok(maven) = std::env::var(MAVEN_URL_ENV) {
+            config.endpoints.set_maven(maven);
+        }
+        if let Ok(modrinth) = std::env::var(MODRINTH_URL_ENV) {
+            config.endpoints.set_modrinth(modrinth);
+        }
+        if let Ok(adoptium) = std::env::var(ADOPTIUM_URL_ENV) {
+            config.endpoints.set_adoptium(adoptium);
+        }
+        if let Some(cache_dir) = std::env::var_os(CACHE_DIR_ENV) {
+            config.cache_dir = Some(PathBuf::from(cache_dir));
+        }
+        if let Some(runtimes_dir) = std::env::var_os(RUNTIMES_DIR_ENV) {
+            config.runtimes_dir = Some(PathBuf::from(runtimes_dir));
+        }
+
+        Ok(config)
+    }
+}
+
+fn read_config_file(path: &Path) -> Result<ConfigFile, InstallerError> {
+    let content = std::fs::read_to_string(path).map_err(|e| {
+        InstallerError::io(format!("Failed to read config file {}", path.display())).caused_by(e)
+    })?;
+    serde_json::from_str(&content).map_err(|e| {
+        InstallerError::parse(format!("Failed to parse config file {}", path.display()))
+            .caused_by(e)
+    })
+}
+
+/// Sets the configuration for this run. Only the first call has an effect.
+pub fn init(config: Config) {
+    let _ = CONFIG.set(config);
+}
+
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(|| Config::load(None).unwrap_or_default())
+}
+
+pub fn default_config_file() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("ornithe-installer").join("config.json"))
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn config_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| crate::ui::home_dir().map(|p| p.join(".config")))
+}
+
+#[cfg(target_os = "windows")]
+fn config_dir() -> Option<PathBuf> {
+    std::env::var_os("APPDATA").map(PathBuf::from)
+}
+
+#[cfg(target_os = "macos")]
+fn config_dir() -> Option<PathBuf> {
+    crate::ui::home_dir().map(|p| p.join("Library/Application Support"))
+}
@@ -1,40 +1,192 @@
-use std::{fmt::Debug, path::StripPrefixError};
+use std::{error::Error, fmt::Display, path::StripPrefixError};
 
+type Source = Box<dyn Error + Send + Sync + 'static>;
+
+/// Everything that can go wrong while installing.
+///
+/// The CLI exits with [`InstallerError::exit_code`], the codes are listed in the README.
 #[derive(Debug)]
-pub struct InstallerError(pub String);
+pub enum InstallerError {
+    /// A request failed or a server answered with an error status.
+    Network {
+        message: String,
+        source: Option<Source>,
+    },
+    /// Reading or writing local files failed.
+    Io {
+        message: String,
+        source: Option<Source>,
+    },
+    /// A document or archive didn't have the expected format.
+    Parse {
+        message: String,
+        source: Option<Source>,
+    },
+    /// A requested version, file or entry doesn't exist.
+    NotFound(String),
+    /// The installation would overwrite or clash with something that already exists.
+    Conflict(String),
+    /// A downloaded file didn't match its expected hash or size.
+    Integrity(String),
+    /// The user aborted the operation.
+    Cancelled,
+    Other {
+        message: String,
+        source: Option<Source>,
+    },
+}
+
+impl InstallerError {
+    pub fn network(message: impl Into<String>) -> Self {
+        InstallerError::Network {
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    pub fn io(message: impl Into<String>) -> Self {
+        InstallerError::Io {
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    pub fn parse(message: impl Into<String>) -> Self {
+        InstallerError::Parse {
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    pub fn other(message: impl Into<String>) -> Self {
+        InstallerError::Other {
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    /// Attaches the error that caused this one, if the variant carries a cause.
+    pub fn caused_by(mut self, cause: impl Into<Source>) -> Self {
+        if let InstallerError::Network { source, .. }
+        | InstallerError::Io { source, .. }
+        | InstallerError::Parse { source, .. }
+        | InstallerError::Other { source, .. } = &mut self
+        {
+            *source = Some(cause.into());
+        }
+        self
+    }
+
+    /// The message followed by the messages of all its causes.
+    pub fn report(&self) -> String {
+        let mut out = self.to_string();
+        let mut source = self.source();
+        while let Some(cause) = source {
+            out += ": ";
+            out += &cause.to_string();
+            source = cause.source();
+        }
+        out
+    }
+
+    /// Status the CLI exits with when it fails with this error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            InstallerError::Other { .. } => 1,
+            InstallerError::Network { .. } => 3,
+            InstallerError::Io { .. } => 4,
+            InstallerError::Parse { .. } => 5,
+            InstallerError::NotFound(_) => 6,
+            InstallerError::Conflict(_) => 7,
+            InstallerError::Integrity(_) => 8,
+            InstallerError::Cancelled => 9,
+        }
+    }
+}
+
+impl Display for InstallerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InstallerError::Network { message, .. }
+            | InstallerError::Io { message, .. }
+            | InstallerError::Parse { message, .. }
+            | InstallerError::Other { message, .. }
+            | InstallerError::NotFound(message)
+            | InstallerError::Conflict(message)
+            | InstallerError::Integrity(message) => f.write_str(message),
+            InstallerError::Cancelled => f.write_str("Cancelled"),
+        }
+    }
+}
+
+impl Error for InstallerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            InstallerError::Network { source, .. }
+            | InstallerError::Io { source, .. }
+            | InstallerError::Parse { source, .. }
+            | InstallerError::Other { source, .. } => source
+                .as_deref()
+                .map(|source| source as &(dyn Error + 'static)),
+            _ => None,
+        }
+    }
+}
 
 impl From<eframe::Error> for InstallerError {
     fn from(value: eframe::Error) -> Self {
-        InstallerError(format!("{:?}", value))
+        InstallerError::other(format!("Failed to open the installer window: {}", value))
     }
 }
 
 impl From<reqwest::Error> for InstallerError {
     fn from(value: reqwest::Error) -> Self {
-        InstallerError(format!("{:?}", value))
+        let message = match value.url() {
+            Some(url) => format!("Request to {} failed", url),
+            None => "Request failed".to_owned(),
+        };
+        let cause = value.without_url();
+        if cause.is_decode() {
+            InstallerError::parse(message).caused_by(cause)
+        } else {
+            InstallerError::network(message).caused_by(cause)
+        }
     }
 }
 
 impl From<serde_json::Error> for InstallerError {
     fn from(value: serde_json::Error) -> Self {
-        InstallerError(format!("{:?}", value))
+        InstallerError::parse("Invalid json").caused_by(value)
     }
 }
 
 impl From<std::io::Error> for InstallerError {
     fn from(value: std::io::Error) -> Self {
-        InstallerError(format!("{:?}", value))
+        InstallerError::io("I/O error").caused_by(value)
     }
 }
 
 impl From<zip::result::ZipError> for InstallerError {
     fn from(value: zip::result::ZipError) -> Self {
-        InstallerError(format!("{:?}", value))
+        match value {
+            zip::result::ZipError::Io(e) => e.into(),
+            e => InstallerError::parse("Invalid archive").caused_by(e),
+        }
     }
 }
 
 impl From<StripPrefixError> for InstallerError {
     fn from(value: StripPrefixError) -> Self {
-        InstallerError(format!("{:?}", value))
+        InstallerError::other("Path is outside of the installation directory").caused_by(value)
+    }
+}
+
+impl From<tokio::task::JoinError> for InstallerError {
+    fn from(value: tokio::task::JoinError) -> Self {
+        if value.is_cancelled() {
+            InstallerError::Cancelled
+        } else {
+            InstallerError::other("Background task failed").caused_by(value)
+        }
     }
 }
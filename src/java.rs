@@ -0,0 +1,324 @@
+//! Discovery of installed Java runtimes and download of managed ones.
+
+use std::{
+    collections::HashSet,
+    fs::File,
+    path::{Path, PathBuf},
+};
+
+use flate2::read::GzDecoder;
+use log::{debug, info, warn};
+use zip::ZipArchive;
+
+use crate::{errors::InstallerError, model::version::JavaVersion, progress::Progress};
+
+/// Java version of game versions whose version json doesn't declare one.
+pub const DEFAULT_JAVA_MAJOR: u32 = 8;
+
+#[cfg(target_os = "windows")]
+const JAVA_BINARY: &str = "java.exe";
+#[cfg(not(target_os = "windows"))]
+const JAVA_BINARY: &str = "java";
+
+/// Directories holding one runtime per subdirectory.
+#[cfg(all(unix, not(target_os = "macos")))]
+const RUNTIME_DIRS: &[&str] = &["/usr/lib/jvm", "/usr/lib64/jvm", "/usr/java", "/opt/java"];
+#[cfg(target_os = "macos")]
+const RUNTIME_DIRS: &[&str] = &["/Library/Java/JavaVirtualMachines"];
+#[cfg(target_os = "windows")]
+const RUNTIME_DIRS: &[&str] = &[
+    r"C:\Program Files\Java",
+    r"C:\Program Files\Eclipse Adoptium",
+];
+
+#[derive(Clone, Debug)]
+pub struct JavaRuntime {
+    pub home: PathBuf,
+    pub binary: PathBuf,
+    /// The `JAVA_VERSION` from the `release` file, e.g. `17.0.8` or `1.8.0_392`.
+    pub version: String,
+    pub major_version: u32,
+}
+
+/// The Java major version a game version requires.
+pub fn required_major(java_version: Option<&JavaVersion>) -> u32 {
+    java_version
+        .map(|java| java.major_version)
+        .unwrap_or(DEFAULT_JAVA_MAJOR)
+}
+
+/// Finds the runtimes in the standard install locations, `JAVA_HOME` and `PATH`.
+pub fn discover() -> Vec<JavaRuntime> {
+    let mut homes = Vec::new();
+    for dir in RUNTIME_DIRS {
+        if let Ok(entries) = std::fs::read_dir(dir) {
+            for entry in entries.flatten() {
+                homes.push(bundle_home(entry.path()));
+            }
+        }
+    }
+    if let Some(home) = std::env::var_os("JAVA_HOME") {
+        homes.push(PathBuf::from(home));
+    }
+    if let Some(path) = std::env::var_os("PATH") {
+        for dir in std::env::split_paths(&path) {
+            let binary = dir.join(JAVA_BINARY);
+            // Binaries on the PATH are usually links into the runtime's bin folder
+            if let Ok(binary) = binary.canonicalize()
+                && let Some(home) = binary.parent().and_then(Path::parent)
+            {
+                homes.push(home.to_owned());
+            }
+        }
+    }
+
+    let mut seen = HashSet::new();
+    let mut runtimes = Vec::new();
+    for home in homes {
+        let Ok(home) = home.canonicalize() else {
+            continue;
+        };
+        if !seen.insert(home.clone()) {
+            continue;
+        }
+        match read_runtime(&home) {
+            Some(runtime) => runtimes.push(runtime),
+            None => debug!("{} is not a Java runtime", home.display()),
+        }
+    }
+    runtimes
+}
+
+/// Picks the runtime matching the required major version, or else the
+/// oldest newer one.
+pub fn find_runtime(required_major: u32) -> Option<JavaRuntime> {
+    let mut runtimes = discover();
+    runtimes.sort_by_key(|runtime| runtime.major_version);
+    runtimes
+        .into_iter()
+        .find(|runtime| runtime.major_version >= required_major)
+}
+
+/// Picks the binary of a runtime of the required major version or newer: one
+/// downloaded before, an installed one or else a freshly downloaded one,
+/// falling back to `java` from the PATH.
+pub async fn select_binary(runtimes_dir: &Path, required: u32, progress: &Progress) -> PathBuf {
+    let runtime =
+        match find_managed_runtime(runtimes_dir, required).or_else(|| find_runtime(required)) {
+            Some(runtime) => Some(runtime),
+            None if crate::config::get().offline => None,
+            None => match download_runtime(runtimes_dir, required, progress).await {
+                Ok(runtime) => Some(runtime),
+                Err(e) => {
+                    warn!(
+                        "Could not download a Java {} runtime: {}",
+                        required,
+                        e.report()
+                    );
+                    None
+                }
+            },
+        };
+    binary_or_path(runtime, required)
+}
+
+/// The binary of a runtime found for the required major version, or else
+/// `java` from the PATH.
+pub fn binary_or_path(runtime: Option<JavaRuntime>, required: u32) -> PathBuf {
+    match runtime {
+        Some(runtime) => {
+            info!(
+                "Using Java {} from {}",
+                runtime.version,
+                runtime.home.display()
+            );
+            runtime.binary
+        }
+        None => {
+            warn!(
+                "Found no Java {} or newer runtime, using java from the PATH",
+                required
+            );
+            PathBuf::from("java")
+        }
+    }
+}
+
+/// The directory a managed runtime of the given major version is kept in.
+pub fn managed_runtime_dir(runtimes_dir: &Path, major: u32) -> PathBuf {
+    runtimes_dir.join(format!("java-{}", major))
+}
+
+/// Finds a runtime previously downloaded to the runtimes directory.
+pub fn find_managed_runtime(runtimes_dir: &Path, major: u32) -> Option<JavaRuntime> {
+    read_runtime(&bundle_home(managed_runtime_dir(runtimes_dir, major)))
+}
+
+/// Downloads the latest runtime of a Java major version from the Adoptium api
+/// and unpacks it below the runtimes directory, replacing any previous one.
+pub async fn download_runtime(
+    runtimes_dir: &Path,
+    major: u32,
+    progress: &Progress,
+) -> Result<JavaRuntime, InstallerError> {
+    let release = crate::net::adoptium::fetch_latest_jre(major).await?;
+    let package = &release.binary.package;
+    if package.name.contains(['/', '\\']) {
+        return Err(InstallerError::parse(format!(
+            "Invalid Java runtime archive name {}",
+            package.name
+        )));
+    }
+
+    info!("Downloading Java runtime {}", release.release_name);
+    progress.phase(format!("Downloading Java {}", major));
+    progress.totals(1, package.size);
+
+    // Unpacked next to the destination, so it can be moved into place as a whole
+    let staging = runtimes_dir.join(format!(".java-{}-download", major));
+    if staging.exists() {
+        std::fs::remove_dir_all(&staging)?;
+    }
+    let archive = staging.join(&package.name);
+    let unpacked = staging.join("unpacked");
+    let result = async {
+        crate::net::download_file(&package.link, &archive, &package.integrity(), progress).await?;
+        unpack(&archive, &unpacked)
+    }
+    .await;
+    if let Err(e) = result {
+        let _ = std::fs::remove_dir_all(&staging);
+        return Err(e);
+    }
+
+    // Archives contain a single folder named after the release
+    let mut entries = std::fs::read_dir(&unpacked)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let root = match entries.len() {
+        1 if entries[0].is_dir() => entries.remove(0),
+        _ => unpacked,
+    };
+
+    let target = managed_runtime_dir(runtimes_dir, major);
+    if target.exists() {
+        std::fs::remove_dir_all(&target)?;
+    }
+    std::fs::rename(&root, &target)?;
+    std::fs::remove_dir_all(&staging)?;
+
+    read_runtime(&bundle_home(target.clone())).ok_or_else(|| {
+        InstallerError::parse(format!(
+            "Downloaded Java runtime at {} is not usable",
+            target.display()
+        ))
+    })
+}
+
+fn unpack(archive: &Path, output: &Path) -> Result<(), InstallerError> {
+    let name = archive.to_string_lossy();
+    if name.ends_with(".zip") {
+        ZipArchive::new(File::open(archive)?)?.extract(output)?;
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        tar::Archive::new(GzDecoder::new(File::open(archive)?)).unpack(output)?;
+    } else {
+        return Err(InstallerError::parse(format!(
+            "Unsupported Java runtime archive {}",
+            name
+        )));
+    }
+    Ok(())
+}
+
+/// macOS bundles keep the runtime below Contents/Home.
+fn bundle_home(home: PathBuf) -> PathBuf {
+    let bundle_home = home.join("Contents/Home");
+    if bundle_home.is_dir() {
+        bundle_home
+    } else {
+        home
+    }
+}
+
+fn read_runtime(home: &Path) -> Option<JavaRuntime> {
+    let binary = home.join("bin").join(JAVA_BINARY);
+    if !binary.is_file() {
+        return None;
+    }
+    let release = std::fs::read_to_string(home.join("release")).ok()?;
+    let version = release.lines().find_map(|line| {
+        line.strip_prefix("JAVA_VERSION=")
+            .map(|value| value.trim().trim_matches('"').to_owned())
+    })?;
+    let major_version = parse_major(&version)?;
+    Some(JavaRuntime {
+        home: home.to_owned(),
+        binary,
+        version,
+        major_version,
+    })
+}
+
+/// Extracts the major version from a Java version, where versions before 9
+/// are written as `1.<major>`.
+fn parse_major(version: &str) -> Option<u32> {
+    let mut parts = version.split(['.', '_', '-', '+']);
+    match parts.next()? {
+        "1" => parts.next()?.parse().ok(),
+        major => major.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A runtime home with a java binary and the given release file.
+    fn runtime_home(name: &str, release: &str) -> PathBuf {
+        let home =
+            std::env::temp_dir().join(format!("ornithe-java-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&home);
+        std::fs::create_dir_all(home.join("bin")).unwrap();
+        std::fs::write(home.join("bin").join(JAVA_BINARY), "").unwrap();
+        std::fs::write(home.join("release"), release).unwrap();
+        home
+    }
+
+    #[test]
+    fn parse_major_handles_old_and_new_versions() {
+        assert_eq!(parse_major("1.8.0_392"), Some(8));
+        assert_eq!(parse_major("1.8"), Some(8));
+        assert_eq!(parse_major("17.0.8+7"), Some(17));
+        assert_eq!(parse_major("21"), Some(21));
+        assert_eq!(parse_major("22-ea"), Some(22));
+        assert_eq!(parse_major("1"), None);
+        assert_eq!(parse_major("java"), None);
+    }
+
+    #[test]
+    fn read_runtime_reads_the_release_file() {
+        let home = runtime_home(
+            "release",
+            "IMPLEMENTOR=\"Eclipse Adoptium\"\nJAVA_VERSION=\"17.0.8\"\n",
+        );
+        let runtime = read_runtime(&home).unwrap();
+        assert_eq!(runtime.version, "17.0.8");
+        assert_eq!(runtime.major_version, 17);
+        assert_eq!(runtime.binary, home.join("bin").join(JAVA_BINARY));
+        std::fs::remove_dir_all(home).unwrap();
+
+        let home = runtime_home("unquoted", "JAVA_VERSION=1.8.0_392\n");
+        assert_eq!(read_runtime(&home).unwrap().major_version, 8);
+        std::fs::remove_dir_all(home).unwrap();
+    }
+
+    #[test]
+    fn read_runtime_skips_homes_without_a_version() {
+        let home = runtime_home("missing", "IMPLEMENTOR=\"Eclipse Adoptium\"\n");
+        assert!(read_runtime(&home).is_none());
+        std::fs::remove_file(home.join("bin").join(JAVA_BINARY)).unwrap();
+        std::fs::write(home.join("release"), "JAVA_VERSION=\"21\"\n").unwrap();
+        assert!(read_runtime(&home).is_none());
+        std::fs::remove_dir_all(home).unwrap();
+    }
+}
@@ -2,8 +2,12 @@ use env_logger::Env;
 use log::info;
 
 mod actions;
+mod config;
 mod errors;
+mod java;
+mod model;
 mod net;
+mod progress;
 mod ui;
 
 static VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -18,10 +22,10 @@ async fn main() {
     info!("Ornithe Installer v{}", VERSION);
 
     // The first argument is the binary name
-    if std::env::args().count() <= 1 {
-        if let Ok(_) = crate::ui::gui::run().await {
-            return;
-        }
+    if std::env::args().count() <= 1
+        && let Ok(_) = crate::ui::gui::run().await
+    {
+        return;
     }
 
     crate::ui::cli::run().await
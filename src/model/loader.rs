@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::version::Arguments;
+
+/// A launch profile as served by the Ornithe meta server, for either side.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LoaderProfile {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inherits_from: Option<String>,
+    #[serde(rename = "type")]
+    pub release_type: String,
+    pub main_class: String,
+    /// Only present in Quilt server profiles.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub launcher_main_class: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<Arguments>,
+    pub libraries: Vec<LoaderLibrary>,
+    /// The intermediary generation the profile was installed with, added by the installer.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub intermediary_generation: Option<u32>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LoaderLibrary {
+    pub name: String,
+    /// Base url of the maven repository the library is hosted on.
+    pub url: String,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
@@ -0,0 +1,113 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::version::{AssetIndex, Library};
+
+pub const FORMAT_VERSION: u32 = 1;
+
+/// The `mmc-pack.json` of a MultiMC/Prism instance, listing its components.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MmcPack {
+    pub components: Vec<MmcComponent>,
+    pub format_version: u32,
+}
+
+impl MmcPack {
+    pub fn new() -> MmcPack {
+        MmcPack {
+            components: Vec::new(),
+            format_version: FORMAT_VERSION,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MmcComponent {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cached_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cached_requires: Vec<MmcRequirement>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cached_version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cached_volatile: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dependency_only: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub important: Option<bool>,
+    pub uid: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct MmcRequirement {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub equals: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suggests: Option<String>,
+    pub uid: String,
+}
+
+/// A component patch, stored in the `patches` folder of an instance.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MmcPatch {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset_index: Option<AssetIndex>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub compatible_java_majors: Vec<u32>,
+    pub format_version: u32,
+    #[serde(default)]
+    pub libraries: Vec<Library>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub main_class: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub main_jar: Option<Library>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub minecraft_arguments: Option<String>,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub release_time: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub requires: Vec<MmcRequirement>,
+    #[serde(rename = "+traits", default, skip_serializing_if = "Vec::is_empty")]
+    pub traits: Vec<String>,
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub release_type: Option<String>,
+    pub uid: String,
+    pub version: String,
+    /// The intermediary generation of an intermediary patch, added by the installer.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub intermediary_generation: Option<u32>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl MmcPatch {
+    /// A patch that only adds libraries to the instance.
+    pub fn new(uid: &str, name: &str, version: &str) -> MmcPatch {
+        MmcPatch {
+            asset_index: None,
+            compatible_java_majors: Vec::new(),
+            format_version: FORMAT_VERSION,
+            libraries: Vec::new(),
+            main_class: None,
+            main_jar: None,
+            minecraft_arguments: None,
+            name: name.to_owned(),
+            release_time: None,
+            requires: Vec::new(),
+            traits: Vec::new(),
+            release_type: Some("release".to_owned()),
+            uid: uid.to_owned(),
+            version: version.to_owned(),
+            intermediary_generation: None,
+            extra: Map::new(),
+        }
+    }
+}
@@ -0,0 +1,18 @@
+//! Typed representations of the launch profiles and packs the installer reads and writes.
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::errors::InstallerError;
+
+pub mod loader;
+pub mod mmc;
+pub mod mrpack;
+pub mod version;
+
+/// Converts a json document into one of the models, describing what was
+/// wrong with it if it doesn't match.
+pub fn parse<T: DeserializeOwned>(document: Value, what: &str) -> Result<T, InstallerError> {
+    serde_json::from_value(document)
+        .map_err(|e| InstallerError::parse(format!("Invalid {}", what)).caused_by(e))
+}
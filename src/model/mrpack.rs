@@ -0,0 +1,60 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+pub const INDEX_FILE: &str = "modrinth.index.json";
+pub const FORMAT_VERSION: u32 = 1;
+
+/// The `modrinth.index.json` at the root of a `.mrpack`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MrpackIndex {
+    pub format_version: u32,
+    pub game: String,
+    pub version_id: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    #[serde(default)]
+    pub files: Vec<MrpackFile>,
+    /// Versions of the game and loaders, e.g. `minecraft` or `fabric-loader`.
+    pub dependencies: BTreeMap<String, String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MrpackFile {
+    /// Destination relative to the game directory.
+    pub path: String,
+    pub hashes: MrpackHashes,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env: Option<MrpackEnv>,
+    /// Urls the file can be downloaded from, tried in order.
+    pub downloads: Vec<String>,
+    pub file_size: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MrpackHashes {
+    pub sha1: String,
+    pub sha512: String,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct MrpackEnv {
+    pub client: EnvSupport,
+    pub server: EnvSupport,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum EnvSupport {
+    Required,
+    Optional,
+    Unsupported,
+}
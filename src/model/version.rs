@@ -0,0 +1,291 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::net::integrity::Integrity;
+
+/// A vanilla version json as read by the official launcher.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionJson {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub release_type: String,
+    pub main_class: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub minecraft_arguments: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<Arguments>,
+    #[serde(default)]
+    pub libraries: Vec<Library>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub downloads: Option<VersionDownloads>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset_index: Option<AssetIndex>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assets: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub java_version: Option<JavaVersion>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub release_time: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logging: Option<Logging>,
+    /// Fields the installer doesn't use, kept so they survive a round trip.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl VersionJson {
+    /// The game arguments as a single string, regardless of the json format version.
+    pub fn game_arguments(&self) -> String {
+        if let Some(arguments) = &self.arguments
+            && !arguments.game.is_empty()
+        {
+            return arguments
+                .game
+                .iter()
+                .filter_map(|arg| match arg {
+                    Argument::Plain(arg) => Some(arg.as_str()),
+                    Argument::Conditional { .. } => None,
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+        }
+        self.minecraft_arguments.clone().unwrap_or_default()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Arguments {
+    #[serde(default)]
+    pub game: Vec<Argument>,
+    #[serde(default)]
+    pub jvm: Vec<Argument>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum Argument {
+    Plain(String),
+    Conditional {
+        rules: Vec<Rule>,
+        value: ArgumentValue,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum ArgumentValue {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Rule {
+    pub action: RuleAction,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub os: Option<OsRule>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub features: Option<Map<String, Value>>,
+}
+
+impl Rule {
+    /// Whether the rule applies to the running system, with none of the
+    /// launcher features (demo mode, custom resolution, ..) enabled. Os
+    /// version constraints are not checked.
+    fn applies(&self) -> bool {
+        let os_matches = self.os.as_ref().is_none_or(|os| {
+            os.name.as_deref().is_none_or(|name| name == os_name())
+                && os.arch.as_deref().is_none_or(|arch| arch == os_arch())
+        });
+        let features_match = self.features.as_ref().is_none_or(|features| {
+            features
+                .values()
+                .all(|enabled| enabled.as_bool() == Some(false))
+        });
+        os_matches && features_match
+    }
+}
+
+/// Whether something guarded by rules is used on the running system. The
+/// last rule that applies decides, nothing is allowed if none applies.
+pub fn rules_allow(rules: &[Rule]) -> bool {
+    rules
+        .iter()
+        .rev()
+        .find(|rule| rule.applies())
+        .is_some_and(|rule| rule.action == RuleAction::Allow)
+}
+
+/// The name of the running os in rules and natives.
+pub fn os_name() -> &'static str {
+    match std::env::consts::OS {
+        "windows" => "windows",
+        "macos" => "osx",
+        _ => "linux",
+    }
+}
+
+/// The arch in os rules, which only ever restrict a library to `x86`.
+fn os_arch() -> &'static str {
+    std::env::consts::ARCH
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleAction {
+    Allow,
+    Disallow,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct OsRule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arch: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Library {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub downloads: Option<LibraryDownloads>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub natives: Option<BTreeMap<String, String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rules: Option<Vec<Rule>>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Library {
+    /// A library resolved from a maven repository.
+    pub fn maven(name: &str, url: &str) -> Library {
+        Library {
+            name: name.to_owned(),
+            url: Some(url.to_owned()),
+            downloads: None,
+            natives: None,
+            rules: None,
+            extra: Map::new(),
+        }
+    }
+
+    /// The artifact id of the library, i.e. the second part of its maven coordinates.
+    pub fn artifact(&self) -> Option<&str> {
+        self.name.split(':').nth(1)
+    }
+
+    pub fn version(&self) -> Option<&str> {
+        self.name.split(':').nth(2)
+    }
+
+    /// Whether the library is used on the running system.
+    pub fn is_allowed(&self) -> bool {
+        self.rules.as_deref().is_none_or(rules_allow)
+    }
+
+    /// The classifier of the natives for the running system, if the library has any.
+    pub fn native_classifier(&self) -> Option<String> {
+        let classifier = self.natives.as_ref()?.get(os_name())?;
+        let bits = if cfg!(target_pointer_width = "32") {
+            "32"
+        } else {
+            "64"
+        };
+        Some(classifier.replace("${arch}", bits))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct LibraryDownloads {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artifact: Option<Artifact>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub classifiers: Option<BTreeMap<String, Artifact>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Artifact {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha1: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    pub url: String,
+}
+
+impl Artifact {
+    pub fn integrity(&self) -> Integrity {
+        Integrity {
+            sha1: self.sha1.clone(),
+            size: self.size,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct VersionDownloads {
+    pub client: Artifact,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server: Option<Artifact>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetIndex {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha1: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_size: Option<u64>,
+    pub url: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Logging {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client: Option<LoggingConfig>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// A log4j configuration and the jvm argument pointing the game to it.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LoggingConfig {
+    /// Contains `${path}` in place of the path of the configuration.
+    pub argument: String,
+    pub file: LoggingFile,
+    #[serde(rename = "type")]
+    pub config_type: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LoggingFile {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha1: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    pub url: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JavaVersion {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub component: Option<String>,
+    pub major_version: u32,
+}
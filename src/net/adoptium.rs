@@ -0,0 +1,80 @@
+use serde::Deserialize;
+
+use crate::errors::InstallerError;
+
+use super::integrity::Integrity;
+
+/// A release as returned by the Adoptium `assets/latest` api.
+#[derive(Deserialize, Clone, Debug)]
+pub struct AdoptiumRelease {
+    pub binary: AdoptiumBinary,
+    pub release_name: String,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct AdoptiumBinary {
+    pub package: AdoptiumPackage,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct AdoptiumPackage {
+    pub name: String,
+    pub link: String,
+    pub size: Option<u64>,
+    /// Sha256 of the archive.
+    pub checksum: Option<String>,
+}
+
+impl AdoptiumPackage {
+    pub fn integrity(&self) -> Integrity {
+        Integrity {
+            sha256: self.checksum.clone(),
+            size: self.size,
+            ..Default::default()
+        }
+    }
+}
+
+/// Fetches the latest JRE of a Java major version built for this platform.
+pub async fn fetch_latest_jre(major: u32) -> Result<AdoptiumRelease, InstallerError> {
+    let (Some(os), Some(arch)) = (os(), architecture()) else {
+        return Err(InstallerError::NotFound(format!(
+            "No Java runtimes are available for {} {}",
+            std::env::consts::OS,
+            std::env::consts::ARCH
+        )));
+    };
+    let url = format!(
+        "{}/v3/assets/latest/{}/hotspot?architecture={}&image_type=jre&os={}&vendor=eclipse",
+        crate::config::get().endpoints.adoptium(),
+        major,
+        arch,
+        os
+    );
+    let releases: Vec<AdoptiumRelease> = super::fetch_json(&url).await?;
+    releases.into_iter().next().ok_or_else(|| {
+        InstallerError::NotFound(format!(
+            "No Java {} runtime is available for {} {}",
+            major, os, arch
+        ))
+    })
+}
+
+fn os() -> Option<&'static str> {
+    match std::env::consts::OS {
+        "linux" => Some("linux"),
+        "macos" => Some("mac"),
+        "windows" => Some("windows"),
+        _ => None,
+    }
+}
+
+fn architecture() -> Option<&'static str> {
+    match std::env::consts::ARCH {
+        "x86_64" => Some("x64"),
+        "x86" => Some("x32"),
+        "aarch64" => Some("aarch64"),
+        "arm" => Some("arm"),
+        _ => None,
+    }
+}
@@ -0,0 +1,173 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use log::{debug, warn};
+use reqwest::{
+    StatusCode,
+    header::{ETAG, HeaderMap, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+};
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+
+use crate::errors::InstallerError;
+
+/// On-disk cache for metadata responses.
+///
+/// Every entry consists of the response body and a small json file holding
+/// the validators (`ETag`/`Last-Modified`) used to revalidate it.
+pub struct HttpCache {
+    dir: PathBuf,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct CacheEntry {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+impl HttpCache {
+    pub fn new(dir: PathBuf) -> HttpCache {
+        HttpCache { dir }
+    }
+
+    fn paths(&self, url: &str) -> (PathBuf, PathBuf) {
+        let key = format!("{:x}", Sha1::digest(url.as_bytes()));
+        (
+            self.dir.join(key.clone() + ".body"),
+            self.dir.join(key + ".json"),
+        )
+    }
+
+    fn read(&self, url: &str) -> Option<(CacheEntry, Vec<u8>)> {
+        let (body_path, entry_path) = self.paths(url);
+        let entry = serde_json::from_slice::<CacheEntry>(&std::fs::read(entry_path).ok()?).ok()?;
+        if entry.url != url {
+            return None;
+        }
+        let body = std::fs::read(body_path).ok()?;
+        Some((entry, body))
+    }
+
+    fn write(&self, url: &str, headers: &HeaderMap, body: &[u8]) -> Result<(), InstallerError> {
+        let (body_path, entry_path) = self.paths(url);
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_owned)
+        };
+        let entry = CacheEntry {
+            url: url.to_owned(),
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        };
+        std::fs::create_dir_all(&self.dir)?;
+        write_atomic(&body_path, body)?;
+        write_atomic(&entry_path, &serde_json::to_vec(&entry)?)?;
+        Ok(())
+    }
+
+    /// Fetches the given url, serving the cached copy if the server reports it
+    /// as unchanged or cannot be reached. In offline mode only the cache is used.
+    pub async fn fetch(&self, url: &str, offline: bool) -> Result<Vec<u8>, InstallerError> {
+        let cached = self.read(url);
+
+        if offline {
+            return cached
+                .map(|(_, body)| body)
+                .ok_or(InstallerError::NotFound(format!(
+                    "{} is not available in the cache while offline",
+                    url
+                )));
+        }
+
+        let response = super::download::with_retries(url, || async {
+            let mut request = super::CLIENT.get(url);
+            if let Some((entry, _)) = &cached {
+                if let Some(etag) = &entry.etag {
+                    request = request.header(IF_NONE_MATCH, etag);
+                }
+                if let Some(last_modified) = &entry.last_modified {
+                    request = request.header(IF_MODIFIED_SINCE, last_modified);
+                }
+            }
+            let response = request.send().await?.error_for_status()?;
+            let status = response.status();
+            let headers = response.headers().clone();
+            Ok((status, headers, response.bytes().await?.to_vec()))
+        })
+        .await;
+
+        let (status, headers, body) = match response {
+            Ok(response) => response,
+            Err(e) => {
+                let e = InstallerError::from(e);
+                if let Some((_, body)) = cached {
+                    warn!(
+                        "Failed to revalidate {}, using cached copy: {}",
+                        url,
+                        e.report()
+                    );
+                    return Ok(body);
+                }
+                return Err(e);
+            }
+        };
+
+        if status == StatusCode::NOT_MODIFIED
+            && let Some((_, body)) = cached
+        {
+            debug!("Using cached copy of {}", url);
+            return Ok(body);
+        }
+
+        if let Err(e) = self.write(url, &headers, &body) {
+            warn!("Failed to cache {}: {}", url, e.report());
+        }
+        Ok(body)
+    }
+}
+
+/// Writes a file through a temporary one next to it. The temporary name is
+/// unique to the write, so concurrent writes of one entry never rename each
+/// other's half-written file into place.
+fn write_atomic(path: &Path, content: &[u8]) -> Result<(), InstallerError> {
+    static WRITES: AtomicUsize = AtomicUsize::new(0);
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(
+        ".{}-{}.tmp",
+        std::process::id(),
+        WRITES.fetch_add(1, Ordering::Relaxed)
+    ));
+    let tmp = path.with_file_name(name);
+    std::fs::write(&tmp, content)?;
+    if let Err(e) = std::fs::rename(&tmp, path) {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+pub fn default_cache_dir() -> Option<PathBuf> {
+    cache_dir().map(|dir| dir.join("ornithe-installer"))
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn cache_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| crate::ui::home_dir().map(|p| p.join(".cache")))
+}
+
+#[cfg(target_os = "windows")]
+fn cache_dir() -> Option<PathBuf> {
+    std::env::var_os("LOCALAPPDATA").map(PathBuf::from)
+}
+
+#[cfg(target_os = "macos")]
+fn cache_dir() -> Option<PathBuf> {
+    crate::ui::home_dir().map(|p| p.join("Library/Caches"))
+}
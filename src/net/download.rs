@@ -0,0 +1,255 @@
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::LazyLock,
+    time::Duration,
+};
+
+use log::{debug, warn};
+use reqwest::{StatusCode, header::RANGE};
+use tokio::sync::Semaphore;
+
+use crate::{errors::InstallerError, progress::Progress};
+
+use super::integrity::Integrity;
+
+const BACKOFF_BASE: Duration = Duration::from_millis(500);
+const BACKOFF_MAX: Duration = Duration::from_secs(30);
+
+/// Limits how many downloads run at the same time across all actions.
+static PERMITS: LazyLock<Semaphore> =
+    LazyLock::new(|| Semaphore::new(crate::config::get().download.max_concurrent.max(1)));
+
+pub struct DownloadSettings {
+    pub connect_timeout: Duration,
+    /// Maximum time to wait for the next chunk of a response.
+    pub read_timeout: Duration,
+    /// How often a failed download is retried before giving up.
+    pub retries: u32,
+    pub max_concurrent: usize,
+}
+
+impl Default for DownloadSettings {
+    fn default() -> Self {
+        DownloadSettings {
+            connect_timeout: Duration::from_secs(15),
+            read_timeout: Duration::from_secs(30),
+            retries: 3,
+            max_concurrent: 8,
+        }
+    }
+}
+
+pub(super) enum Failure {
+    /// The server rejected the request, asking again won't help.
+    Fatal(InstallerError),
+    Transient(InstallerError),
+}
+
+impl From<reqwest::Error> for Failure {
+    fn from(value: reqwest::Error) -> Self {
+        match value.status() {
+            Some(status) if is_fatal(status) => Failure::Fatal(value.into()),
+            _ => Failure::Transient(value.into()),
+        }
+    }
+}
+
+impl From<std::io::Error> for Failure {
+    fn from(value: std::io::Error) -> Self {
+        Failure::Fatal(value.into())
+    }
+}
+
+impl From<Failure> for InstallerError {
+    fn from(value: Failure) -> Self {
+        match value {
+            Failure::Fatal(e) | Failure::Transient(e) => e,
+        }
+    }
+}
+
+fn is_fatal(status: StatusCode) -> bool {
+    status.is_client_error()
+        && status != StatusCode::REQUEST_TIMEOUT
+        && status != StatusCode::TOO_MANY_REQUESTS
+}
+
+/// Runs a request, retrying transient failures with an exponential backoff.
+/// The request is made anew for every attempt, so nothing it holds is kept
+/// while waiting for the next one.
+pub(super) async fn with_retries<T, F, Fut>(url: &str, mut request: F) -> Result<T, Failure>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, Failure>>,
+{
+    let retries = crate::config::get().download.retries;
+    let mut attempt = 0;
+    loop {
+        match request().await {
+            Err(Failure::Transient(e)) if attempt < retries => {
+                let delay = BACKOFF_BASE
+                    .saturating_mul(2u32.saturating_pow(attempt))
+                    .min(BACKOFF_MAX);
+                attempt += 1;
+                warn!(
+                    "Download of {} failed ({}), retrying in {:.1}s ({}/{})",
+                    url,
+                    e.report(),
+                    delay.as_secs_f32(),
+                    attempt,
+                    retries
+                );
+                tokio::time::sleep(delay).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Downloads a file, checking it against the given integrity information.
+///
+/// The body is streamed into a `.part` file next to the output, which is
+/// resumed on retries and moved into place once it has been verified. A
+/// resumed file that fails the check is downloaded once more from the start.
+/// An existing file that already matches a known hash is reused.
+pub async fn download_file(
+    url: &str,
+    output: &Path,
+    integrity: &Integrity,
+    progress: &Progress,
+) -> Result<(), InstallerError> {
+    if integrity.matches_existing(output) {
+        debug!("Reusing {}", output.display());
+        progress.file_finished(output, integrity.size);
+        return Ok(());
+    }
+    if crate::config::get().offline {
+        if output.exists() && !integrity.is_known() {
+            progress.file_finished(output, integrity.size);
+            return Ok(());
+        }
+        return Err(InstallerError::network(format!(
+            "Cannot download {} while offline",
+            url
+        )));
+    }
+
+    let url = super::resolve_url(url);
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let part = part_file(output);
+
+    let mut restarted = false;
+    loop {
+        let resumed = download_part(&url, &part, output, progress).await?;
+        let Some(mismatch) = integrity.check(&part)? else {
+            break;
+        };
+        std::fs::remove_file(&part)?;
+        if resumed && !restarted {
+            // The partial file was left by an older version of the resource
+            warn!(
+                "Resumed download of {} is corrupt ({}), downloading it again",
+                url, mismatch
+            );
+            restarted = true;
+            continue;
+        }
+        return Err(InstallerError::Integrity(format!(
+            "Downloaded file {} from {} is corrupt: {}",
+            output.display(),
+            url,
+            mismatch
+        )));
+    }
+
+    if output.exists() {
+        std::fs::remove_file(output)?;
+    }
+    std::fs::rename(&part, output)?;
+    progress.file_finished(output, integrity.size);
+
+    Ok(())
+}
+
+/// Downloads into the `.part` file, retrying transient failures. Returns
+/// whether the attempt that completed it resumed a partial file.
+async fn download_part(
+    url: &str,
+    part: &Path,
+    output: &Path,
+    progress: &Progress,
+) -> Result<bool, InstallerError> {
+    let result = with_retries(url, || async {
+        // Only hold a slot while downloading, not while backing off
+        let _permit = PERMITS
+            .acquire()
+            .await
+            .map_err(|_| Failure::Fatal(InstallerError::Cancelled))?;
+        try_download(url, part, output, progress).await
+    })
+    .await;
+    match result {
+        Ok(resumed) => Ok(resumed),
+        // Keep the partial file around so a later run can resume it
+        Err(Failure::Transient(e)) => Err(e),
+        Err(Failure::Fatal(e)) => {
+            let _ = std::fs::remove_file(part);
+            Err(e)
+        }
+    }
+}
+
+/// Makes a single attempt at the download, returning whether it resumed
+/// a partial file.
+async fn try_download(
+    url: &str,
+    part: &Path,
+    output: &Path,
+    progress: &Progress,
+) -> Result<bool, Failure> {
+    let resume_from = std::fs::metadata(part).map(|m| m.len()).unwrap_or(0);
+
+    let mut request = super::CLIENT.get(url);
+    if resume_from > 0 {
+        debug!("Resuming download of {} at {} bytes", url, resume_from);
+        request = request.header(RANGE, format!("bytes={}-", resume_from));
+    }
+    let response = request.send().await?;
+
+    if response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+        // The partial file doesn't belong to this resource, start over
+        std::fs::remove_file(part)?;
+        return Err(Failure::Transient(InstallerError::network(
+            "Server rejected the resumed range",
+        )));
+    }
+    let mut response = response.error_for_status()?;
+
+    let resumed = response.status() == StatusCode::PARTIAL_CONTENT;
+    let (mut file, mut downloaded) = if resumed {
+        (OpenOptions::new().append(true).open(part)?, resume_from)
+    } else {
+        (std::fs::File::create(part)?, 0)
+    };
+    let total = response.content_length().map(|len| len + downloaded);
+
+    while let Some(chunk) = response.chunk().await? {
+        file.write_all(&chunk)?;
+        downloaded += chunk.len() as u64;
+        progress.file_progress(output, downloaded, total);
+    }
+    file.flush()?;
+
+    Ok(resumed)
+}
+
+/// The file a download to `output` is streamed into before it is complete.
+pub fn part_file(output: &Path) -> PathBuf {
+    let mut name = output.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    output.with_file_name(name)
+}
@@ -0,0 +1,112 @@
+pub const DEFAULT_META_URL: &str = "https://meta.ornithemc.net";
+pub const DEFAULT_MC_VERSIONS_URL: &str = "https://skyrising.github.io/mc-versions";
+pub const DEFAULT_MAVEN_URL: &str = "https://maven.ornithemc.net/releases";
+pub const DEFAULT_MODRINTH_URL: &str = "https://api.modrinth.com";
+pub const DEFAULT_ADOPTIUM_URL: &str = "https://api.adoptium.net";
+
+/// The base urls the installer talks to.
+///
+/// Metadata served by these hosts contains absolute urls of its own (version
+/// details, sub-manifests, library repositories), so overriding a base url also
+/// registers a mirror that rewrites the corresponding public prefix.
+pub struct Endpoints {
+    meta: String,
+    mc_versions: String,
+    maven: String,
+    modrinth: String,
+    adoptium: String,
+    mirrors: Vec<(String, String)>,
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Endpoints {
+            meta: DEFAULT_META_URL.to_owned(),
+            mc_versions: DEFAULT_MC_VERSIONS_URL.to_owned(),
+            maven: DEFAULT_MAVEN_URL.to_owned(),
+            modrinth: DEFAULT_MODRINTH_URL.to_owned(),
+            adoptium: DEFAULT_ADOPTIUM_URL.to_owned(),
+            mirrors: Vec::new(),
+        }
+    }
+}
+
+impl Endpoints {
+    pub fn meta(&self) -> &str {
+        &self.meta
+    }
+
+    pub fn maven(&self) -> &str {
+        &self.maven
+    }
+
+    /// Base url of the Modrinth-compatible api mods are resolved from.
+    pub fn modrinth(&self) -> &str {
+        &self.modrinth
+    }
+
+    /// Base url of the Adoptium-compatible api Java runtimes are downloaded from.
+    pub fn adoptium(&self) -> &str {
+        &self.adoptium
+    }
+
+    pub fn version_manifest(&self) -> String {
+        self.mc_versions.clone() + "/version_manifest.json"
+    }
+
+    pub fn version_json(&self, version_id: &str) -> String {
+        format!("{}/version/manifest/{}.json", self.mc_versions, version_id)
+    }
+
+    pub fn set_meta(&mut self, url: String) {
+        self.meta = trim_url(url);
+        self.add_mirror(DEFAULT_META_URL.to_owned(), self.meta.clone());
+    }
+
+    pub fn set_mc_versions(&mut self, url: String) {
+        self.mc_versions = trim_url(url);
+        self.add_mirror(DEFAULT_MC_VERSIONS_URL.to_owned(), self.mc_versions.clone());
+    }
+
+    pub fn set_maven(&mut self, url: String) {
+        self.maven = trim_url(url);
+        self.add_mirror(DEFAULT_MAVEN_URL.to_owned(), self.maven.clone());
+    }
+
+    pub fn set_modrinth(&mut self, url: String) {
+        self.modrinth = trim_url(url);
+        self.add_mirror(DEFAULT_MODRINTH_URL.to_owned(), self.modrinth.clone());
+    }
+
+    pub fn set_adoptium(&mut self, url: String) {
+        self.adoptium = trim_url(url);
+        self.add_mirror(DEFAULT_ADOPTIUM_URL.to_owned(), self.adoptium.clone());
+    }
+
+    /// Registers a prefix rewrite. Later registrations for the same prefix
+    /// replace earlier ones.
+    pub fn add_mirror(&mut self, from: String, to: String) {
+        let from = trim_url(from);
+        let to = trim_url(to);
+        self.mirrors.retain(|(f, _)| *f != from);
+        if from != to {
+            self.mirrors.push((from, to));
+        }
+    }
+
+    /// Rewrites a url to point at the configured mirror, if any.
+    pub fn resolve(&self, url: &str) -> String {
+        for (from, to) in &self.mirrors {
+            if let Some(rest) = url.strip_prefix(from.as_str())
+                && (rest.is_empty() || rest.starts_with('/'))
+            {
+                return to.clone() + rest;
+            }
+        }
+        url.to_owned()
+    }
+}
+
+fn trim_url(url: String) -> String {
+    url.trim_end_matches('/').to_owned()
+}
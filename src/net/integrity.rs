@@ -0,0 +1,87 @@
+use std::{fs::File, path::Path};
+
+use sha1::{Digest, Sha1};
+use sha2::{Sha256, Sha512};
+
+use crate::errors::InstallerError;
+
+/// What a downloaded file is expected to look like.
+#[derive(Clone, Default, Debug)]
+pub struct Integrity {
+    pub sha1: Option<String>,
+    pub sha512: Option<String>,
+    pub sha256: Option<String>,
+    pub size: Option<u64>,
+}
+
+impl Integrity {
+    pub fn sha1(sha1: impl Into<String>) -> Integrity {
+        Integrity {
+            sha1: Some(sha1.into()),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_size(mut self, size: u64) -> Integrity {
+        self.size = Some(size);
+        self
+    }
+
+    /// Whether there is a hash to check files against.
+    pub fn is_known(&self) -> bool {
+        self.sha1.is_some() || self.sha512.is_some() || self.sha256.is_some()
+    }
+
+    /// Checks the file against the expected size and hash, returning a
+    /// description of the first mismatch found.
+    pub fn check(&self, file: &Path) -> Result<Option<String>, InstallerError> {
+        if let Some(size) = self.size {
+            let actual = std::fs::metadata(file)?.len();
+            if actual != size {
+                return Ok(Some(format!("expected {} bytes, got {}", size, actual)));
+            }
+        }
+        if let Some(sha1) = &self.sha1 {
+            let actual = sha1_file(file)?;
+            if !actual.eq_ignore_ascii_case(sha1) {
+                return Ok(Some(format!("expected sha1 {}, got {}", sha1, actual)));
+            }
+        }
+        if let Some(sha256) = &self.sha256 {
+            let actual = sha256_file(file)?;
+            if !actual.eq_ignore_ascii_case(sha256) {
+                return Ok(Some(format!("expected sha256 {}, got {}", sha256, actual)));
+            }
+        }
+        if let Some(sha512) = &self.sha512 {
+            let actual = sha512_file(file)?;
+            if !actual.eq_ignore_ascii_case(sha512) {
+                return Ok(Some(format!("expected sha512 {}, got {}", sha512, actual)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Whether the file exists and matches a known hash, so it can be reused.
+    pub fn matches_existing(&self, file: &Path) -> bool {
+        self.is_known() && file.is_file() && matches!(self.check(file), Ok(None))
+    }
+}
+
+pub fn sha1_file(file: &Path) -> Result<String, InstallerError> {
+    let mut hasher = Sha1::new();
+    std::io::copy(&mut File::open(file)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+pub fn sha512_file(file: &Path) -> Result<String, InstallerError> {
+    let mut hasher = Sha512::new();
+    std::io::copy(&mut File::open(file)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+pub fn sha256_file(file: &Path) -> Result<String, InstallerError> {
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut File::open(file)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
@@ -0,0 +1,154 @@
+use std::{
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+use serde::Deserialize;
+
+/// Describes a loader Ornithe can be installed with.
+///
+/// The built-in definitions can be overridden and extended with the `loaders`
+/// section of the config file.
+#[derive(Deserialize, Debug)]
+pub struct LoaderDefinition {
+    /// Short name used on the command line and in file names, e.g. `fabric`.
+    pub id: String,
+    /// Name shown to users, e.g. `Fabric`.
+    pub name: String,
+    /// Path of the loader below `/v3/versions/` on the meta server.
+    pub meta_path: String,
+    /// Maven group and artifact of the loader, e.g. `net.fabricmc:fabric-loader`.
+    pub maven: String,
+    #[serde(default)]
+    pub server: ServerLaunch,
+    /// Paths in a server directory that hold loader caches and are cleared on install.
+    #[serde(default)]
+    pub cleanup_paths: Vec<String>,
+}
+
+/// How the launch jar of a server installation starts the loader.
+#[derive(Deserialize, Debug, Default)]
+pub struct ServerLaunch {
+    /// Main class of the launch jar. If unset, the `launcherMainClass` of the
+    /// server profile is used.
+    pub launcher_main_class: Option<String>,
+    /// File in the launch jar that receives the main class of the server
+    /// profile as `launch.mainClass`.
+    pub properties_file: Option<String>,
+}
+
+/// A registered loader. Loaders are compared by their id.
+#[derive(Clone, Debug)]
+pub struct LoaderType(Arc<LoaderDefinition>);
+
+impl LoaderType {
+    pub fn new(definition: LoaderDefinition) -> LoaderType {
+        LoaderType(Arc::new(definition))
+    }
+
+    pub fn definition(&self) -> &LoaderDefinition {
+        &self.0
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.0.id
+    }
+
+    pub fn get_localized_name(&self) -> &str {
+        &self.0.name
+    }
+
+    pub fn get_maven_uid(&self) -> String {
+        self.0.maven.replace(':', ".")
+    }
+
+    pub fn get_maven_name_start(&self) -> &str {
+        &self.0.maven
+    }
+
+    pub fn get_meta_path(&self) -> &str {
+        &self.0.meta_path
+    }
+}
+
+impl PartialEq for LoaderType {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.id == other.0.id
+    }
+}
+
+impl Eq for LoaderType {}
+
+impl Hash for LoaderType {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.id.hash(state);
+    }
+}
+
+/// The loaders available in this run, in the order they are offered.
+#[derive(Debug)]
+pub struct LoaderRegistry {
+    loaders: Vec<LoaderType>,
+}
+
+impl Default for LoaderRegistry {
+    fn default() -> Self {
+        LoaderRegistry {
+            loaders: vec![
+                LoaderType::new(LoaderDefinition {
+                    id: "fabric".to_owned(),
+                    name: "Fabric".to_owned(),
+                    meta_path: "fabric-loader".to_owned(),
+                    maven: "net.fabricmc:fabric-loader".to_owned(),
+                    server: ServerLaunch {
+                        launcher_main_class: Some(
+                            "net.fabricmc.loader.launch.server.FabricServerLauncher".to_owned(),
+                        ),
+                        properties_file: Some("fabric-server-launch.properties".to_owned()),
+                    },
+                    cleanup_paths: vec![".fabric".to_owned()],
+                }),
+                LoaderType::new(LoaderDefinition {
+                    id: "quilt".to_owned(),
+                    name: "Quilt".to_owned(),
+                    meta_path: "quilt-loader".to_owned(),
+                    maven: "org.quiltmc:quilt-loader".to_owned(),
+                    server: ServerLaunch::default(),
+                    cleanup_paths: vec![".quilt".to_owned()],
+                }),
+            ],
+        }
+    }
+}
+
+impl LoaderRegistry {
+    /// Adds a loader, replacing any loader with the same id.
+    pub fn register(&mut self, definition: LoaderDefinition) {
+        let loader = LoaderType::new(definition);
+        match self.loaders.iter_mut().find(|l| **l == loader) {
+            Some(existing) => *existing = loader,
+            None => self.loaders.push(loader),
+        }
+    }
+
+    pub fn all(&self) -> &[LoaderType] {
+        &self.loaders
+    }
+
+    /// Looks up a loader by its id, ignoring case.
+    pub fn get(&self, id: &str) -> Option<&LoaderType> {
+        self.loaders
+            .iter()
+            .find(|loader| loader.get_name().eq_ignore_ascii_case(id))
+    }
+
+    /// The loader selected when none is given.
+    pub fn default_loader(&self) -> &LoaderType {
+        // The built-in loaders can be replaced but never removed
+        &self.loaders[0]
+    }
+
+    pub fn ids(&self) -> Vec<&str> {
+        self.loaders.iter().map(LoaderType::get_name).collect()
+    }
+}
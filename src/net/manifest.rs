@@ -2,87 +2,72 @@ use chrono::{DateTime, Utc};
 use serde::Deserialize;
 use serde_json::{Map, Value};
 
-use crate::errors::InstallerError;
+use crate::{
+    errors::InstallerError,
+    model::version::{Library, VersionJson},
+};
 
-use super::GameSide;
-
-const LAUNCHER_META_URL: &str = "https://skyrising.github.io/mc-versions/version_manifest.json";
-const VERSION_META_URL: &str = "https://skyrising.github.io/mc-versions/version/manifest/{}.json";
+use super::{GameSide, integrity::Integrity, resolver::MetadataResolver};
 
 pub async fn fetch_versions() -> Result<VersionManifest, InstallerError> {
-    super::CLIENT
-        .get(LAUNCHER_META_URL)
-        .send()
-        .await?
-        .json::<VersionManifest>()
-        .await
-        .map_err(|e| e.into())
+    super::fetch_json(&crate::config::get().endpoints.version_manifest()).await
 }
 
-pub async fn fetch_launch_json(version: &MinecraftVersion) -> Result<String, InstallerError> {
-    let res = super::CLIENT
-        .get(VERSION_META_URL.replace("{}", version.id.as_str()))
-        .send()
-        .await?;
-    if let Some(val) = res.json::<Value>().await?.as_object_mut() {
-        let version_details = fetch_version_details(&version).await?;
-
-        for manifest in version_details.manifests {
-            if let Some(manifest) = super::CLIENT
-                .get(manifest.url)
-                .send()
-                .await?
-                .json::<Value>()
-                .await?
-                .as_object()
-            {
-                build_version_json_from_manifest(val, manifest);
-            }
+pub async fn fetch_launch_json(
+    version: &MinecraftVersion,
+    resolver: &MetadataResolver,
+) -> Result<VersionJson, InstallerError> {
+    let mut res = (*resolver
+        .document(&crate::config::get().endpoints.version_json(&version.id))
+        .await?)
+        .clone();
+    let Some(val) = res.as_object_mut() else {
+        return Err(InstallerError::parse(format!(
+            "Version json of {} is not an object",
+            version.id
+        )));
+    };
+    let version_details = resolver.version_details(version).await?;
+
+    for manifest in &version_details.manifests {
+        if let Some(manifest) = resolver.document(&manifest.url).await?.as_object() {
+            build_version_json_from_manifest(val, manifest);
         }
+    }
 
-        val.insert(
-            "id".to_string(),
-            Value::String(format!("{}-vanilla", version.id.clone())),
-        );
+    val.insert(
+        "id".to_string(),
+        Value::String(format!("{}-vanilla", version.id.clone())),
+    );
 
-        return Ok(serde_json::to_string_pretty(val)?);
-    }
-    Err(InstallerError("Error".to_string()))
+    super::resolve_urls(&mut res);
+
+    crate::model::parse(res, &format!("version json of {}", version.id))
 }
 
 fn build_version_json_from_manifest(
     version_json: &mut Map<String, Value>,
     manifest: &Map<String, Value>,
 ) {
-    for entry in manifest {
-        if version_json.contains_key(entry.0) {
-            let version_json_element = version_json.get_mut(entry.0).unwrap();
-            let manifest_element = entry.1;
-
-            if version_json_element != manifest_element
-                && version_json_element.is_object()
-                && manifest_element.is_object()
-            {
-                build_version_json_from_manifest(
-                    version_json_element.as_object_mut().unwrap(),
-                    manifest_element.as_object().unwrap(),
-                );
+    for (key, manifest_element) in manifest {
+        match version_json.get_mut(key) {
+            Some(Value::Object(version_json_element)) => {
+                if let Value::Object(manifest_element) = manifest_element {
+                    build_version_json_from_manifest(version_json_element, manifest_element);
+                }
+            }
+            Some(_) => {}
+            None => {
+                version_json.insert(key.to_string(), manifest_element.clone());
             }
-        } else {
-            version_json.insert(entry.0.to_string(), entry.1.clone());
         }
     }
 }
 
-async fn fetch_version_details(
+pub(super) async fn fetch_version_details(
     version: &MinecraftVersion,
-) -> Result<VersionDetails, reqwest::Error> {
-    super::CLIENT
-        .get(version.details.clone())
-        .send()
-        .await?
-        .json::<VersionDetails>()
-        .await
+) -> Result<VersionDetails, InstallerError> {
+    super::fetch_json(&version.details).await
 }
 
 #[allow(dead_code)]
@@ -118,8 +103,12 @@ pub struct MinecraftVersion {
 }
 
 impl MinecraftVersion {
-    pub async fn get_id(&self, side: &GameSide) -> Result<String, reqwest::Error> {
-        if fetch_version_details(self).await?.shared_mappings {
+    pub async fn get_id(
+        &self,
+        side: &GameSide,
+        resolver: &MetadataResolver,
+    ) -> Result<String, InstallerError> {
+        if resolver.version_details(self).await?.shared_mappings {
             Ok(self.id.clone())
         } else {
             Ok(self.id.clone() + "-" + side.id())
@@ -129,11 +118,12 @@ impl MinecraftVersion {
     pub async fn get_jar_download_url(
         &self,
         side: &GameSide,
-    ) -> Result<VersionDownload, reqwest::Error> {
-        let downloads = fetch_version_details(self).await?.downloads;
+        resolver: &MetadataResolver,
+    ) -> Result<VersionDownload, InstallerError> {
+        let details = resolver.version_details(self).await?;
         Ok(match side {
-            GameSide::Client => downloads.client,
-            GameSide::Server => downloads.server,
+            GameSide::Client => details.downloads.client.clone(),
+            GameSide::Server => details.downloads.server.clone(),
         })
     }
 
@@ -167,14 +157,19 @@ struct VersionDownloads {
     server: VersionDownload,
 }
 
-#[allow(dead_code)]
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone)]
 pub struct VersionDownload {
     pub sha1: String,
-    pub size: u32,
+    pub size: u64,
     pub url: String,
 }
 
+impl VersionDownload {
+    pub fn integrity(&self) -> Integrity {
+        Integrity::sha1(&self.sha1).with_size(self.size)
+    }
+}
+
 #[derive(Deserialize)]
 pub struct VersionDetailsManifest {
     #[serde(rename = "type")]
@@ -182,31 +177,33 @@ pub struct VersionDetailsManifest {
     url: String,
 }
 
-pub async fn find_lwjgl_version(version: &MinecraftVersion) -> Result<String, InstallerError> {
-    let details = fetch_version_details(&version).await?;
-    for manifest in details.manifests {
-        let manifest = super::CLIENT
-            .get(manifest.url)
-            .send()
-            .await?
-            .json::<Value>()
-            .await?;
-
-        if let Some(libs) = manifest["libraries"].as_array() {
-            for library in libs {
-                let name = library["name"].clone();
-                if name.is_string() {
-                    let mut name = name.as_str().unwrap().split(":").skip(1);
-                    let artifact = name.next().unwrap();
-                    if artifact == "lwjgl" {
-                        return Ok(name.next().unwrap().to_owned());
-                    }
-                }
-            }
+pub async fn find_lwjgl_version(
+    version: &MinecraftVersion,
+    resolver: &MetadataResolver,
+) -> Result<String, InstallerError> {
+    let details = resolver.version_details(version).await?;
+    for manifest in &details.manifests {
+        let manifest = (*resolver.document(&manifest.url).await?).clone();
+        let manifest: ManifestLibraries = crate::model::parse(manifest, "version manifest")?;
+
+        if let Some(lwjgl) = manifest
+            .libraries
+            .iter()
+            .find(|library| library.artifact() == Some("lwjgl"))
+            && let Some(version) = lwjgl.version()
+        {
+            return Ok(version.to_owned());
         }
     }
 
-    Err(InstallerError(
+    Err(InstallerError::NotFound(
         "Unable to find lwjgl version for Minecraft ".to_owned() + &version.id,
     ))
 }
+
+/// The part of a version sub-manifest needed to look up library versions.
+#[derive(Deserialize)]
+struct ManifestLibraries {
+    #[serde(default)]
+    libraries: Vec<Library>,
+}
@@ -1,13 +1,15 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt::Display, str::FromStr};
 
 use serde::Deserialize;
-use serde_json::Value;
 
-use crate::errors::InstallerError;
+use crate::{
+    errors::InstallerError,
+    model::loader::{LoaderLibrary, LoaderProfile},
+};
 
-use super::{GameSide, manifest::MinecraftVersion};
-
-const META_URL: &str = "https://meta.ornithemc.net";
+use super::{
+    GameSide, loaders::LoaderType, manifest::MinecraftVersion, resolver::MetadataResolver,
+};
 
 #[allow(dead_code)]
 #[derive(Deserialize, Clone)]
@@ -31,38 +33,52 @@ impl LoaderVersion {
     }
 }
 
-#[derive(PartialEq, Eq, Hash, Clone)]
-pub enum LoaderType {
-    Fabric,
-    Quilt,
-}
+/// A generation of Ornithe's intermediary mappings. Each generation has its
+/// own intermediary artifact and its own meta endpoints.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct IntermediaryGen(pub u32);
 
-impl LoaderType {
-    pub fn get_name(&self) -> &str {
-        match self {
-            LoaderType::Fabric => "fabric",
-            LoaderType::Quilt => "quilt",
+impl IntermediaryGen {
+    /// The generations offered for selection.
+    pub const KNOWN: [IntermediaryGen; 2] = [IntermediaryGen(1), IntermediaryGen(2)];
+
+    /// Path on the meta server below which the versions for this generation are listed.
+    fn versions_path(&self) -> String {
+        match self.0 {
+            1 => "/v3/versions".to_owned(),
+            generation => format!("/v3/versions/gen{}", generation),
         }
     }
 
-    pub fn get_localized_name(&self) -> &str {
-        match self {
-            LoaderType::Fabric => "Fabric",
-            LoaderType::Quilt => "Quilt",
+    /// Maven group and artifact of the intermediary mappings.
+    pub fn maven_artifact(&self) -> String {
+        match self.0 {
+            1 => "net.ornithemc:calamus-intermediary".to_owned(),
+            generation => format!("net.ornithemc:calamus-intermediary-gen{}", generation),
         }
     }
+}
 
-    pub fn get_maven_uid(&self) -> &str {
-        match self {
-            LoaderType::Fabric => "net.fabricmc.fabric-loader",
-            LoaderType::Quilt => "org.quiltmc.quilt-loader",
-        }
+impl Default for IntermediaryGen {
+    fn default() -> Self {
+        IntermediaryGen(1)
     }
+}
 
-    pub fn get_maven_name_start(&self) -> &str {
-        match self {
-            LoaderType::Fabric => "net.fabricmc:fabric-loader",
-            LoaderType::Quilt => "org.quiltmc:quilt-loader",
+impl Display for IntermediaryGen {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Gen {}", self.0)
+    }
+}
+
+impl FromStr for IntermediaryGen {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let number = s.strip_prefix("gen").unwrap_or(s);
+        match number.parse::<u32>() {
+            Ok(generation) if generation > 0 => Ok(IntermediaryGen(generation)),
+            _ => Err(format!("'{}' is not an intermediary generation", s)),
         }
     }
 }
@@ -70,8 +86,8 @@ impl LoaderType {
 impl GameSide {
     fn launch_json_endpoint(&self) -> &str {
         match self {
-            GameSide::Client => "/v3/versions/{}-loader/{}/{}/profile/json",
-            GameSide::Server => "/v3/versions/{}-loader/{}/{}/server/json",
+            GameSide::Client => "{}/{}/{}/{}/profile/json",
+            GameSide::Server => "{}/{}/{}/{}/server/json",
         }
     }
 }
@@ -81,64 +97,72 @@ pub async fn fetch_launch_json(
     version: &MinecraftVersion,
     loader_type: &LoaderType,
     loader_version: &LoaderVersion,
-) -> Result<String, InstallerError> {
-    let mut text = super::CLIENT
-        .get(
-            META_URL.to_owned()
-                + &side
-                    .launch_json_endpoint()
-                    .replacen("{}", loader_type.get_name(), 1)
-                    .replacen("{}", version.get_id(&side).await?.as_str(), 1)
-                    .replacen("{}", &loader_version.version, 1),
+    intermediary_gen: IntermediaryGen,
+    resolver: &MetadataResolver,
+) -> Result<LoaderProfile, InstallerError> {
+    let mut document = (*resolver
+        .document(
+            &launch_json_url(
+                &side,
+                version,
+                loader_type,
+                &loader_version.version,
+                intermediary_gen,
+                resolver,
+            )
+            .await?,
         )
-        .send()
-        .await?
-        .json::<Value>()
-        .await?;
-    if let Some(libraries) = text["libraries"].as_object_mut() {
-        for lib in libraries {
-            let lib_mut = lib.1.as_object_mut().unwrap();
-            if let Some(name) = lib_mut.clone()["name"].as_str() {
-                if name.starts_with("net.fabricmc:intermediary") {
-                    lib_mut.insert(
-                        "name".to_string(),
-                        Value::String(name.replace(
-                            "net.fabricmc:intermediary",
-                            "net.ornithemc:calamus-intermediary",
-                        )),
-                    );
-                    lib_mut.insert(
-                        "url".to_string(),
-                        Value::String("https://maven.ornithemc.net/releases".to_string()),
-                    );
-                }
-                if name.starts_with("org.quiltmc:hashed") {
-                    lib_mut.insert(
-                        "name".to_string(),
-                        Value::String(
-                            name.replace(
-                                "org.quiltmc:hashed",
-                                "net.ornithemc:calamus-intermediary",
-                            ),
-                        ),
-                    );
-                    lib_mut.insert(
-                        "url".to_string(),
-                        Value::String("https://maven.ornithemc.net/releases".to_string()),
-                    );
-                }
+        .await?)
+        .clone();
+    super::resolve_urls(&mut document);
+    let mut profile: LoaderProfile = crate::model::parse(
+        document,
+        &format!("{} launch profile", loader_type.get_localized_name()),
+    )?;
+
+    for lib in &mut profile.libraries {
+        for intermediary in ["net.fabricmc:intermediary", "org.quiltmc:hashed"] {
+            if lib.name.starts_with(intermediary) {
+                lib.name = lib
+                    .name
+                    .replace(intermediary, &intermediary_gen.maven_artifact());
+                lib.url = crate::config::get().endpoints.maven().to_owned() + "/";
             }
         }
     }
-    Ok(serde_json::to_string_pretty(&text)?)
+    profile.intermediary_generation = Some(intermediary_gen.0);
+    Ok(profile)
+}
+
+/// The url of the launch json for a loader version on the meta server.
+pub async fn launch_json_url(
+    side: &GameSide,
+    version: &MinecraftVersion,
+    loader_type: &LoaderType,
+    loader_version: &str,
+    intermediary_gen: IntermediaryGen,
+    resolver: &MetadataResolver,
+) -> Result<String, InstallerError> {
+    Ok(meta_url(
+        &side
+            .launch_json_endpoint()
+            .replacen("{}", &intermediary_gen.versions_path(), 1)
+            .replacen("{}", loader_type.get_meta_path(), 1)
+            .replacen("{}", version.get_id(side, resolver).await?.as_str(), 1)
+            .replacen("{}", loader_version, 1),
+    ))
+}
+
+fn meta_url(path: &str) -> String {
+    crate::config::get().endpoints.meta().to_owned() + path
 }
 
 pub async fn fetch_loader_versions()
 -> Result<HashMap<LoaderType, Vec<LoaderVersion>>, InstallerError> {
     let mut out = HashMap::new();
-    for loader in [LoaderType::Fabric, LoaderType::Quilt] {
-        let versions = fetch_loader_versions_type(&loader).await?;
-        out.insert(loader, versions);
+    for loader in crate::config::get().loaders.all() {
+        let versions = fetch_loader_versions_type(loader).await?;
+        out.insert(loader.clone(), versions);
     }
     Ok(out)
 }
@@ -146,19 +170,8 @@ pub async fn fetch_loader_versions()
 async fn fetch_loader_versions_type(
     loader_type: &LoaderType,
 ) -> Result<Vec<LoaderVersion>, InstallerError> {
-    let url = META_URL.to_owned()
-        + "/v3/versions/"
-        + match loader_type {
-            LoaderType::Fabric => "fabric-loader",
-            LoaderType::Quilt => "quilt-loader",
-        };
-    super::CLIENT
-        .get(url)
-        .send()
-        .await?
-        .json::<Vec<LoaderVersion>>()
-        .await
-        .map_err(|e| e.into())
+    let url = meta_url(&("/v3/versions/".to_owned() + loader_type.get_meta_path()));
+    super::fetch_json(&url).await
 }
 
 #[allow(dead_code)]
@@ -171,15 +184,13 @@ pub struct IntermediaryVersion {
     pub version_no_side: String,
 }
 
-pub async fn fetch_intermediary_versions()
--> Result<HashMap<String, IntermediaryVersion>, InstallerError> {
-    let versions = super::CLIENT
-        .get(META_URL.to_owned() + "/v3/versions/intermediary")
-        .send()
-        .await?
-        .json::<Vec<IntermediaryVersion>>()
-        .await
-        .map_err(|e| Into::<InstallerError>::into(e))?;
+pub async fn fetch_intermediary_versions(
+    intermediary_gen: IntermediaryGen,
+) -> Result<HashMap<String, IntermediaryVersion>, InstallerError> {
+    let versions = super::fetch_json::<Vec<IntermediaryVersion>>(&meta_url(
+        &(intermediary_gen.versions_path() + "/intermediary"),
+    ))
+    .await?;
     let mut out = HashMap::with_capacity(versions.len());
     for ver in versions {
         out.insert(ver.version.clone(), ver);
@@ -187,44 +198,33 @@ pub async fn fetch_intermediary_versions()
     Ok(out)
 }
 
-#[allow(dead_code)]
-#[derive(Deserialize)]
-struct ProfileJson {
-    id: String,
-    libraries: Vec<ProfileJsonLibrary>,
-}
-
-#[derive(Deserialize)]
-pub struct ProfileJsonLibrary {
-    pub name: String,
-    pub url: String,
-}
-
 pub async fn fetch_profile_libraries(
     version: &IntermediaryVersion,
     loader_type: &LoaderType,
     loader_version: &LoaderVersion,
-) -> Result<Vec<ProfileJsonLibrary>, InstallerError> {
-    let profile = super::CLIENT
-        .get(
-            META_URL.to_owned()
-                + &format!(
-                    "/v3/versions/{}-loader/{}/{}/profile/json",
-                    loader_type.get_name(),
-                    version.version,
-                    loader_version.version
-                ),
-        )
-        .send()
-        .await?
-        .json::<ProfileJson>()
-        .await?;
+    intermediary_gen: IntermediaryGen,
+    resolver: &MetadataResolver,
+) -> Result<Vec<LoaderLibrary>, InstallerError> {
+    let profile: LoaderProfile = crate::model::parse(
+        (*resolver
+            .document(&meta_url(&format!(
+                "{}/{}/{}/{}/profile/json",
+                intermediary_gen.versions_path(),
+                loader_type.get_meta_path(),
+                version.version,
+                loader_version.version
+            )))
+            .await?)
+            .clone(),
+        &format!("{} launch profile", loader_type.get_localized_name()),
+    )?;
 
     let mut out = Vec::new();
     let mut loader_found = false;
 
-    for lib in profile.libraries {
+    for mut lib in profile.libraries {
         if loader_found {
+            lib.url = super::resolve_url(&lib.url);
             out.push(lib);
             continue;
         }
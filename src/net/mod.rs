@@ -1,32 +1,122 @@
-use std::{path::PathBuf, sync::LazyLock};
+use std::sync::LazyLock;
 
+use cache::HttpCache;
 use reqwest::Client;
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::Value;
 
 use crate::errors::InstallerError;
 
+pub mod adoptium;
+pub mod cache;
+mod download;
+pub mod endpoints;
+pub mod integrity;
+pub mod loaders;
 pub mod manifest;
 pub mod meta;
+pub mod modrinth;
+pub mod resolver;
+
+pub use download::{DownloadSettings, download_file, part_file};
 
 static CLIENT: LazyLock<Client> = LazyLock::new(|| {
+    let settings = &crate::config::get().download;
     Client::builder()
         .user_agent(crate::USER_AGENT)
+        .connect_timeout(settings.connect_timeout)
+        .read_timeout(settings.read_timeout)
         .build()
         .unwrap()
 });
 
-pub async fn download_file(url: &str, output: &PathBuf) -> Result<(), InstallerError> {
-    let bytes = CLIENT.get(url).send().await?.bytes().await?;
-    if let Some(parent) = output.parent() {
-        if !std::fs::exists(parent)? {
-            std::fs::create_dir_all(parent)?;
-        }
-    }
-    if std::fs::exists(output).unwrap_or(false) {
-        std::fs::remove_file(output)?;
+static CACHE: LazyLock<Option<HttpCache>> = LazyLock::new(|| {
+    crate::config::get()
+        .cache_dir
+        .clone()
+        .map(|dir| HttpCache::new(dir.join("http")))
+});
+
+/// Fetches and parses a json document, going through the metadata cache.
+async fn fetch_json<T: DeserializeOwned>(url: &str) -> Result<T, InstallerError> {
+    Ok(serde_json::from_slice(&fetch_bytes(url).await?)?)
+}
+
+/// Posts a json document and parses the response. Queries are answered by
+/// the server every time, so they bypass the metadata cache.
+async fn post_json<T: DeserializeOwned>(
+    url: &str,
+    body: &impl Serialize,
+) -> Result<T, InstallerError> {
+    let url = resolve_url(url);
+    if crate::config::get().offline {
+        return Err(InstallerError::network(format!(
+            "Cannot query {} while offline",
+            url
+        )));
     }
-    std::fs::write(output, bytes)?;
+    let response = download::with_retries(&url, || async {
+        let response = CLIENT
+            .post(&url)
+            .json(body)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(response.bytes().await?.to_vec())
+    })
+    .await?;
+    Ok(serde_json::from_slice(&response)?)
+}
+
+/// Fetches a small text document, going through the metadata cache.
+pub async fn fetch_text(url: &str) -> Result<String, InstallerError> {
+    String::from_utf8(fetch_bytes(url).await?).map_err(|e| {
+        InstallerError::parse(format!("{} did not return valid text", url)).caused_by(e)
+    })
+}
+
+async fn fetch_bytes(url: &str) -> Result<Vec<u8>, InstallerError> {
+    let url = resolve_url(url);
+    let offline = crate::config::get().offline;
+    let body = match &*CACHE {
+        Some(cache) => cache.fetch(&url, offline).await?,
+        None if offline => {
+            return Err(InstallerError::network(format!(
+                "Cannot fetch {} while offline without a cache",
+                url
+            )));
+        }
+        None => {
+            download::with_retries(&url, || async {
+                let response = CLIENT.get(&url).send().await?.error_for_status()?;
+                Ok(response.bytes().await?.to_vec())
+            })
+            .await?
+        }
+    };
+    Ok(body)
+}
 
-    Ok(())
+/// Rewrites a url to point at a configured mirror, if any.
+pub fn resolve_url(url: &str) -> String {
+    crate::config::get().endpoints.resolve(url)
+}
+
+/// Rewrites every `url` field in a json document to point at the configured
+/// mirrors, so generated files never reference a replaced host.
+pub fn resolve_urls(json: &mut Value) {
+    match json {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                match value {
+                    Value::String(url) if key == "url" => *url = resolve_url(url),
+                    _ => resolve_urls(value),
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(resolve_urls),
+        _ => {}
+    }
 }
 
 pub enum GameSide {
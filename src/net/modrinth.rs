@@ -0,0 +1,134 @@
+use std::{collections::HashMap, error::Error};
+
+use reqwest::{StatusCode, Url};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::errors::InstallerError;
+
+use super::integrity::Integrity;
+
+/// A version of a project as returned by the Modrinth api.
+#[allow(dead_code)]
+#[derive(Deserialize, Clone, Debug)]
+pub struct ModrinthVersion {
+    pub id: String,
+    pub project_id: String,
+    pub version_number: String,
+    pub files: Vec<ModrinthFile>,
+    #[serde(default)]
+    pub dependencies: Vec<Dependency>,
+    #[serde(default)]
+    pub game_versions: Vec<String>,
+    #[serde(default)]
+    pub loaders: Vec<String>,
+}
+
+impl ModrinthVersion {
+    /// The file to install, the one marked as primary or else the first.
+    pub fn primary_file(&self) -> Option<&ModrinthFile> {
+        self.files
+            .iter()
+            .find(|file| file.primary)
+            .or(self.files.first())
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct ModrinthFile {
+    pub url: String,
+    pub filename: String,
+    #[serde(default)]
+    pub primary: bool,
+    pub size: Option<u64>,
+    pub hashes: FileHashes,
+}
+
+impl ModrinthFile {
+    pub fn integrity(&self) -> Integrity {
+        Integrity {
+            sha1: self.hashes.sha1.clone(),
+            sha512: self.hashes.sha512.clone(),
+            size: self.size,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct FileHashes {
+    pub sha1: Option<String>,
+    pub sha512: Option<String>,
+}
+
+#[allow(dead_code)]
+#[derive(Deserialize, Clone, Debug)]
+pub struct Dependency {
+    pub version_id: Option<String>,
+    pub project_id: Option<String>,
+    pub file_name: Option<String>,
+    pub dependency_type: String,
+}
+
+/// Fetches the versions of a project that support the given game version and
+/// loader, newest first.
+pub async fn fetch_project_versions(
+    project: &str,
+    game_version: &str,
+    loader: &str,
+) -> Result<Vec<ModrinthVersion>, InstallerError> {
+    let base = crate::config::get().endpoints.modrinth().to_owned() + "/v2/project/";
+    let mut url = Url::parse(&base).map_err(|e| {
+        InstallerError::parse(format!("Invalid Modrinth api url {}", base)).caused_by(e)
+    })?;
+    url.path_segments_mut()
+        .map_err(|_| InstallerError::parse(format!("Invalid Modrinth api url {}", base)))?
+        .pop_if_empty()
+        .extend([project, "version"]);
+    url.query_pairs_mut()
+        .append_pair("loaders", &serde_json::to_string(&[loader])?)
+        .append_pair("game_versions", &serde_json::to_string(&[game_version])?);
+    super::fetch_json(url.as_str()).await
+}
+
+/// Looks up the version a file belongs to by its sha1 hash. Fails with
+/// [`InstallerError::NotFound`] if the api doesn't know the file.
+pub async fn fetch_version_by_hash(sha1: &str) -> Result<ModrinthVersion, InstallerError> {
+    let url = format!(
+        "{}/v2/version_file/{}?algorithm=sha1",
+        crate::config::get().endpoints.modrinth(),
+        sha1
+    );
+    super::fetch_json(&url).await.map_err(|e| {
+        let status = e
+            .source()
+            .and_then(|source| source.downcast_ref::<reqwest::Error>())
+            .and_then(reqwest::Error::status);
+        if status == Some(StatusCode::NOT_FOUND) {
+            InstallerError::NotFound(format!("No version contains a file with sha1 {}", sha1))
+        } else {
+            e
+        }
+    })
+}
+
+/// Looks up the versions files belong to by their sha1 hashes, in one request.
+/// Hashes of files the api doesn't know are missing from the result.
+pub async fn fetch_versions_by_hashes(
+    sha1s: &[String],
+) -> Result<HashMap<String, ModrinthVersion>, InstallerError> {
+    let url = format!(
+        "{}/v2/version_files",
+        crate::config::get().endpoints.modrinth()
+    );
+    super::post_json(&url, &json!({ "hashes": sha1s, "algorithm": "sha1" })).await
+}
+
+pub async fn fetch_version(id: &str) -> Result<ModrinthVersion, InstallerError> {
+    let url = format!(
+        "{}/v2/version/{}",
+        crate::config::get().endpoints.modrinth(),
+        id
+    );
+    super::fetch_json(&url).await
+}
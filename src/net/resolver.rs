@@ -0,0 +1,109 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex},
+};
+
+use serde_json::Value;
+use tokio::sync::OnceCell;
+
+use crate::errors::InstallerError;
+
+use super::{
+    loaders::LoaderType,
+    manifest::{self, MinecraftVersion, VersionDetails, VersionManifest},
+    meta::{self, IntermediaryGen, IntermediaryVersion, LoaderVersion},
+};
+
+/// Fetches metadata at most once per run and hands out the cached copies.
+///
+/// One resolver is shared by everything that runs during a session, so listing
+/// versions and installing one of them doesn't download the same documents twice.
+#[derive(Default)]
+pub struct MetadataResolver {
+    versions: OnceCell<VersionManifest>,
+    intermediary_versions: Memo<HashMap<String, IntermediaryVersion>>,
+    loader_versions: OnceCell<HashMap<LoaderType, Vec<LoaderVersion>>>,
+    version_details: Memo<VersionDetails>,
+    documents: Memo<Value>,
+}
+
+impl MetadataResolver {
+    pub fn new() -> MetadataResolver {
+        MetadataResolver::default()
+    }
+
+    pub async fn versions(&self) -> Result<&VersionManifest, InstallerError> {
+        self.versions
+            .get_or_try_init(manifest::fetch_versions)
+            .await
+    }
+
+    pub async fn intermediary_versions(
+        &self,
+        intermediary_gen: IntermediaryGen,
+    ) -> Result<Arc<HashMap<String, IntermediaryVersion>>, InstallerError> {
+        self.intermediary_versions
+            .get_or_fetch(
+                &intermediary_gen.0.to_string(),
+                meta::fetch_intermediary_versions(intermediary_gen),
+            )
+            .await
+    }
+
+    pub async fn loader_versions(
+        &self,
+    ) -> Result<&HashMap<LoaderType, Vec<LoaderVersion>>, InstallerError> {
+        self.loader_versions
+            .get_or_try_init(meta::fetch_loader_versions)
+            .await
+    }
+
+    pub async fn version_details(
+        &self,
+        version: &MinecraftVersion,
+    ) -> Result<Arc<VersionDetails>, InstallerError> {
+        self.version_details
+            .get_or_fetch(&version.id, manifest::fetch_version_details(version))
+            .await
+    }
+
+    /// Fetches a json document, such as a version manifest or a launch profile.
+    pub async fn document(&self, url: &str) -> Result<Arc<Value>, InstallerError> {
+        self.documents
+            .get_or_fetch(url, super::fetch_json::<Value>(url))
+            .await
+    }
+}
+
+/// Results of keyed fetches. Concurrent requests for the same key share a
+/// single fetch.
+struct Memo<T> {
+    entries: Mutex<HashMap<String, Arc<OnceCell<Arc<T>>>>>,
+}
+
+impl<T> Default for Memo<T> {
+    fn default() -> Self {
+        Memo {
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<T> Memo<T> {
+    async fn get_or_fetch<F>(&self, key: &str, fetch: F) -> Result<Arc<T>, InstallerError>
+    where
+        F: Future<Output = Result<T, InstallerError>>,
+    {
+        let cell = self
+            .entries
+            .lock()
+            .unwrap()
+            .entry(key.to_owned())
+            .or_default()
+            .clone();
+        cell.get_or_try_init(|| async { fetch.await.map(Arc::new) })
+            .await
+            .cloned()
+    }
+}
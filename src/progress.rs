@@ -0,0 +1,184 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::mpsc::Sender,
+};
+
+use log::info;
+
+/// Something that happened during an installation.
+#[derive(Debug, Clone)]
+pub enum ProgressEvent {
+    /// A new step of the installation started.
+    Phase(String),
+    /// The number of files (and bytes, if known) the current step will download.
+    Totals { files: usize, bytes: Option<u64> },
+    FileProgress {
+        file: PathBuf,
+        downloaded: u64,
+        total: Option<u64>,
+    },
+    /// A file is in place, with its size if it is known. Files that were
+    /// reused rather than downloaded finish without any progress.
+    FileFinished { file: PathBuf, size: Option<u64> },
+}
+
+/// Handle the actions report their progress through.
+///
+/// Without an observer, phase changes are logged instead.
+#[derive(Clone, Default)]
+pub struct Progress {
+    sender: Option<Sender<ProgressEvent>>,
+}
+
+impl Progress {
+    pub fn new(sender: Sender<ProgressEvent>) -> Progress {
+        Progress {
+            sender: Some(sender),
+        }
+    }
+
+    fn send(&self, event: ProgressEvent) {
+        if let Some(sender) = &self.sender {
+            // The observer going away must not abort the installation
+            let _ = sender.send(event);
+        }
+    }
+
+    pub fn phase(&self, name: impl Into<String>) {
+        let name = name.into();
+        if self.sender.is_none() {
+            info!("{}", name);
+        }
+        self.send(ProgressEvent::Phase(name));
+    }
+
+    pub fn totals(&self, files: usize, bytes: Option<u64>) {
+        self.send(ProgressEvent::Totals { files, bytes });
+    }
+
+    pub fn file_progress(&self, file: &Path, downloaded: u64, total: Option<u64>) {
+        self.send(ProgressEvent::FileProgress {
+            file: file.to_path_buf(),
+            downloaded,
+            total,
+        });
+    }
+
+    pub fn file_finished(&self, file: &Path, size: Option<u64>) {
+        self.send(ProgressEvent::FileFinished {
+            file: file.to_path_buf(),
+            size,
+        });
+    }
+}
+
+/// Accumulated progress of the current phase, shared by the progress displays.
+#[derive(Default)]
+pub struct ProgressState {
+    pub phase: String,
+    total_files: usize,
+    total_bytes: Option<u64>,
+    finished: HashSet<PathBuf>,
+    downloaded: HashMap<PathBuf, u64>,
+    current_file: Option<(PathBuf, Option<u64>)>,
+}
+
+impl ProgressState {
+    /// Applies an event, returning whether it started a new phase.
+    pub fn apply(&mut self, event: ProgressEvent) -> bool {
+        match event {
+            ProgressEvent::Phase(name) => {
+                *self = ProgressState {
+                    phase: name,
+                    ..Default::default()
+                };
+                return true;
+            }
+            ProgressEvent::Totals { files, bytes } => {
+                self.total_files = files;
+                self.total_bytes = bytes;
+            }
+            ProgressEvent::FileProgress {
+                file,
+                downloaded,
+                total,
+            } => {
+                self.downloaded.insert(file.clone(), downloaded);
+                self.current_file = Some((file, total));
+            }
+            ProgressEvent::FileFinished { file, size } => {
+                if self.current_file.as_ref().is_some_and(|(f, _)| *f == file) {
+                    self.current_file = None;
+                }
+                // Counts reused files towards the total of known sizes
+                if let Some(size) = size {
+                    self.downloaded.entry(file.clone()).or_insert(size);
+                }
+                self.finished.insert(file);
+            }
+        }
+        false
+    }
+
+    pub fn downloaded_bytes(&self) -> u64 {
+        self.downloaded.values().sum()
+    }
+
+    /// How far along the current phase is, if that is known.
+    pub fn fraction(&self) -> Option<f32> {
+        if let Some(total) = self.total_bytes
+            && total > 0
+        {
+            return Some((self.downloaded_bytes() as f32 / total as f32).min(1.0));
+        }
+        if self.total_files > 0 {
+            return Some((self.finished.len() as f32 / self.total_files as f32).min(1.0));
+        }
+        None
+    }
+
+    /// A short description of the downloads in the current phase.
+    pub fn summary(&self) -> String {
+        let mut out = String::new();
+        if self.total_files > 0 {
+            out += &format!("{}/{} files", self.finished.len(), self.total_files);
+        }
+        let downloaded = self.downloaded_bytes();
+        if downloaded > 0 {
+            if !out.is_empty() {
+                out += ", ";
+            }
+            out += &format_bytes(downloaded);
+            if let Some(total) = self.total_bytes {
+                out += &(" / ".to_owned() + &format_bytes(total));
+            }
+        }
+        if let Some((file, total)) = &self.current_file
+            && let Some(name) = file.file_name()
+        {
+            let downloaded = self.downloaded.get(file).copied().unwrap_or_default();
+            out += &format!(" ({}: {}", name.to_string_lossy(), format_bytes(downloaded));
+            if let Some(total) = total {
+                out += &(" / ".to_owned() + &format_bytes(*total));
+            }
+            out += ")";
+        }
+        out.trim_start().to_owned()
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
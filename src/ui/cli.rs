@@ -1,19 +1,41 @@
-use std::{io::Write, path::PathBuf};
+use std::{
+    collections::HashMap,
+    io::{IsTerminal, Write},
+    path::PathBuf,
+    sync::mpsc::Receiver,
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
 
-use clap::{ArgMatches, Command, arg, command, value_parser};
+use clap::{Arg, ArgAction, ArgMatches, Command, arg, command, value_parser};
 use log::info;
 
 use crate::{
+    actions::{
+        client::ProfileOptions,
+        inventory::Installation,
+        launch::LaunchOptions,
+        mods::{ModSelection, OslSource},
+        mrpack::Modpack,
+        plan::Plan,
+        update::UpdateTarget,
+    },
+    config::Config,
     errors::InstallerError,
     net::{
+        loaders::LoaderType,
         manifest::MinecraftVersion,
-        meta::{LoaderType, LoaderVersion},
+        meta::{IntermediaryGen, LoaderVersion},
+        resolver::MetadataResolver,
     },
+    progress::{Progress, ProgressEvent, ProgressState},
 };
 
 #[derive(PartialEq, Eq)]
 enum InstallationResult {
     Installed,
+    /// Installed together with the Ornithe Standard Libraries.
+    InstalledWithOsl,
     NotInstalled,
 }
 
@@ -21,6 +43,30 @@ pub async fn run() {
     let matches = command!()
         .arg_required_else_help(true)
         .name("Ornithe Installer")
+        .arg(arg!(--config <FILE> "Configuration file to use")
+            .global(true)
+            .value_parser(value_parser!(PathBuf)))
+        .arg(arg!(--"meta-url" <URL> "Base url of the Ornithe meta server").global(true))
+        .arg(arg!(--"mc-versions-url" <URL> "Base url of the Minecraft version manifests").global(true))
+        .arg(arg!(--"maven-url" <URL> "Base url of the Ornithe maven repository").global(true))
+        .arg(arg!(--"modrinth-url" <URL> "Base url of the Modrinth-compatible api").global(true))
+        .arg(arg!(--"adoptium-url" <URL> "Base url of the Adoptium-compatible api Java runtimes are downloaded from").global(true))
+        .arg(arg!(--"cache-dir" <DIR> "Directory to cache metadata in")
+            .global(true)
+            .value_parser(value_parser!(PathBuf)))
+        .arg(arg!(--"runtimes-dir" <DIR> "Shared directory for downloaded Java runtimes")
+            .global(true)
+            .value_parser(value_parser!(PathBuf)))
+        .arg(arg!(--offline "Only use cached metadata and never access the network").global(true))
+        .arg(arg!(--timeout <SECONDS> "Network timeout for connecting and reading")
+            .global(true)
+            .value_parser(value_parser!(u64)))
+        .arg(arg!(--retries <COUNT> "How often to retry failed downloads")
+            .global(true)
+            .value_parser(value_parser!(u32)))
+        .arg(arg!(--"max-downloads" <COUNT> "Maximum number of concurrent downloads")
+            .global(true)
+            .value_parser(value_parser!(usize)))
         .subcommand(
             add_arguments(Command::new("client")
                 .about("Client installation for the official launcher")
@@ -34,7 +80,25 @@ pub async fn run() {
                     arg!(-p --"generate-profile" <VALUE> "Whether to generate a launch profile")
                     .default_value("true")
                         .value_parser(value_parser!(bool)),
-                )),
+                )
+                .arg(arg!(--"profile-name" <NAME> "Name of the launch profile"))
+                .arg(
+                    arg!(--"java-args" <ARGS> "JVM arguments of the launch profile, e.g. -Xmx4G")
+                        .allow_hyphen_values(true),
+                )
+                .arg(
+                    arg!(--"game-dir" <DIR> "Separate game directory for the launch profile, mods are installed there")
+                        .value_parser(value_parser!(PathBuf)),
+                )
+                .arg(
+                    arg!(--icon <PNG> "PNG image to use as icon of the launch profile")
+                        .value_parser(value_parser!(PathBuf)),
+                )
+                .arg(
+                    arg!(--"java-dir" <PATH> "Java executable of the launch profile")
+                        .value_parser(value_parser!(PathBuf)),
+                )
+                .arg(arg!(--"offline-ready" "Also install the client jar, libraries and assets, so the game starts without network access"))),
         )
         .subcommand(
             add_arguments(Command::new("mmc")
@@ -68,6 +132,110 @@ pub async fn run() {
                     .arg(arg!(--java <PATH> "The java binary to use to run the server").value_parser(value_parser!(PathBuf))
                 )),
         ))
+        .subcommand(
+            Command::new("uninstall")
+                .about("Remove the files of an installation, keeping worlds, configs and mods")
+                .subcommand_required(true)
+                .subcommand(uninstall_arguments(Command::new("client")
+                    .about("Remove Ornithe profiles from the official launcher")
+                    .arg(
+                        arg!(-d --dir <DIR> "Installation directory")
+                            .default_value(super::dot_minecraft_location())
+                            .value_parser(value_parser!(PathBuf)),
+                    )))
+                .subcommand(uninstall_arguments(Command::new("mmc")
+                    .visible_alias("prism")
+                    .about("Remove generated MultiMC/PrismLauncher instances")
+                    .arg(
+                        arg!(-d --dir <DIR> "Instance, or the directory instances were generated in")
+                            .default_value(super::current_location())
+                            .value_parser(value_parser!(PathBuf)),
+                    )))
+                .subcommand(Command::new("server")
+                    .about("Remove a server installation")
+                    .arg(
+                        arg!(-d --dir <DIR> "Installation directory")
+                            .default_value(super::server_location())
+                            .value_parser(value_parser!(PathBuf)),
+                    )
+                    .arg(arg!(--"loader-type" <TYPE> "Only remove installations of this loader type"))),
+        )
+        .subcommand(
+            Command::new("update")
+                .about("Update existing installations to another loader version")
+                .subcommand_required(true)
+                .subcommand(update_arguments(Command::new("client")
+                    .about("Update Ornithe profiles of the official launcher")
+                    .arg(
+                        arg!(-d --dir <DIR> "Installation directory")
+                            .default_value(super::dot_minecraft_location())
+                            .value_parser(value_parser!(PathBuf)),
+                    )))
+                .subcommand(update_arguments(Command::new("mmc")
+                    .visible_alias("prism")
+                    .about("Update generated MultiMC/PrismLauncher instances")
+                    .arg(
+                        arg!(-d --dir <DIR> "Instance, or the directory instances were generated in")
+                            .default_value(super::current_location())
+                            .value_parser(value_parser!(PathBuf)),
+                    )))
+                .subcommand(update_arguments(Command::new("server")
+                    .about("Update a server installation")
+                    .arg(
+                        arg!(-d --dir <DIR> "Installation directory")
+                            .default_value(super::server_location())
+                            .value_parser(value_parser!(PathBuf)),
+                    ))),
+        )
+        .subcommand(
+            Command::new("installed")
+                .about("List the Ornithe installations of the official launcher, MultiMC/Prism and servers")
+                .arg(
+                    arg!(-d --dir <DIR> "Game directory of the official launcher")
+                        .action(ArgAction::Append)
+                        .default_value(super::dot_minecraft_location())
+                        .value_parser(value_parser!(PathBuf)),
+                )
+                .arg(arg!(--instances <DIR> "Instance folder to scan, defaults to those of the installed launchers")
+                    .action(ArgAction::Append)
+                    .value_parser(value_parser!(PathBuf)))
+                .arg(arg!(--server <DIR> "Server directory to scan")
+                    .action(ArgAction::Append)
+                    .value_parser(value_parser!(PathBuf)))
+                .arg(arg!(--json "Print the installations as json")),
+        )
+        .subcommand(
+            Command::new("launch-client")
+                .about("Launch an offline-ready client installation without a launcher")
+                .arg(
+                    arg!(-d --dir <DIR> "Installation directory")
+                        .default_value(super::dot_minecraft_location())
+                        .value_parser(value_parser!(PathBuf)),
+                )
+                .arg(arg!(--profile <ID> "Ornithe profile to launch if the directory contains several"))
+                .arg(arg!(-m --"minecraft-version" <VERSION> "Only consider profiles of this Minecraft version"))
+                .arg(arg!(--"loader-type" <TYPE> "Only consider profiles of this loader type"))
+                .arg(arg!(-u --username <NAME> "Name of the offline player").default_value("Player"))
+                .arg(arg!(--java <PATH> "The java binary to launch the game with").value_parser(value_parser!(PathBuf)))
+                .arg(
+                    arg!(--"java-args" <ARGS> "Additional JVM arguments, e.g. -Xmx4G")
+                        .allow_hyphen_values(true),
+                )
+                .arg(
+                    arg!(--"game-dir" <DIR> "Separate game directory to launch the game in")
+                        .value_parser(value_parser!(PathBuf)),
+                )
+                .arg(arg!(--"print-command" "Print the command line instead of launching the game")),
+        )
+        .subcommand(
+            Command::new("export")
+                .about("Export a MultiMC/Prism instance or a game directory as a Modrinth modpack")
+                .arg(arg!(<SOURCE> "Instance or game directory to export").value_parser(value_parser!(PathBuf)))
+                .arg(arg!(-o --output <FILE> "Modpack file to write, defaults to <name>.mrpack").value_parser(value_parser!(PathBuf)))
+                .arg(arg!(--profile <ID> "Ornithe profile to export if the game directory contains several"))
+                .arg(arg!(--name <NAME> "Name of the modpack, defaults to the name of the source directory"))
+                .arg(arg!(--"pack-version" <VERSION> "Version of the modpack").default_value("1.0.0")),
+        )
         .subcommand(
             Command::new("game-versions")
             .alias("minecraft-versions")
@@ -75,42 +243,155 @@ pub async fn run() {
             .long_flag_alias("list-minecraft-versions")
                 .about("List supported game versions")
                 .arg(arg!(-s --"show-snapshots" "Include snapshot versions"))
-                .arg(arg!(--"show-historical" "Include historical versions")),
+                .arg(arg!(--"show-historical" "Include historical versions"))
+                .arg(intermediary_gen_arg()),
         )
         .subcommand(
             Command::new("loader-versions")
             .long_flag("list-loader-versions")
                 .about("List available loader versions")
                 .arg(arg!(-b --"show-betas" "Include beta versions"))
-                .arg(arg!(--"loader-type" <TYPE> "Loader type to use")
-                .default_value("fabric")
-                .ignore_case(true)
-                .value_parser(["fabric", "quilt"])),
+                .arg(arg!(--"loader-type" <TYPE> "Loader type to use, e.g. fabric or quilt")
+                .default_value("fabric")),
         )
         .get_matches();
 
-    match parse(matches).await {
+    match load_config(&matches) {
+        Ok(config) => crate::config::init(config),
+        Err(e) => exit_with_error("Failed to load configuration", e),
+    }
+
+    let (progress, renderer) = spawn_progress_renderer();
+    let result = parse(matches, progress).await;
+    let _ = renderer.join();
+
+    match result {
         Ok(r) => {
-            if r == InstallationResult::Installed {
+            if r != InstallationResult::NotInstalled {
                 info!("Installation complete!");
                 info!("Ornithe has been successfully installed.");
+            }
+            if r == InstallationResult::Installed {
                 info!(
                     "Most mods require that you also download the Ornithe Standard Libraries mod and place it in your mods folder."
                 );
                 info!("You can find it at {}", crate::OSL_MODRINTH_URL);
             }
         }
-        Err(e) => {
-            std::io::stderr()
-                .write_all(("Failed to load Ornithe Installer CLI: ".to_owned() + &e.0).as_bytes())
-                .expect("Failed to print error!");
+        Err(e) => exit_with_error("Error", e),
+    }
+}
+
+/// Prints the error with its causes and exits with the status for its kind.
+fn exit_with_error(context: &str, error: InstallerError) -> ! {
+    let _ = writeln!(std::io::stderr(), "{}: {}", context, error.report());
+    std::process::exit(error.exit_code())
+}
+
+fn load_config(matches: &ArgMatches) -> Result<Config, InstallerError> {
+    let mut config = Config::load(matches.get_one::<PathBuf>("config").cloned())?;
+    if let Some(url) = matches.get_one::<String>("meta-url") {
+        config.endpoints.set_meta(url.clone());
+    }
+    if let Some(url) = matches.get_one::<String>("mc-versions-url") {
+        config.endpoints.set_mc_versions(url.clone());
+    }
+    if let Some(url) = matches.get_one::<String>("maven-url") {
+        config.endpoints.set_maven(url.clone());
+    }
+    if let Some(url) = matches.get_one::<String>("modrinth-url") {
+        config.endpoints.set_modrinth(url.clone());
+    }
+    if let Some(url) = matches.get_one::<String>("adoptium-url") {
+        config.endpoints.set_adoptium(url.clone());
+    }
+    if let Some(dir) = matches.get_one::<PathBuf>("cache-dir") {
+        config.cache_dir = Some(dir.clone());
+    }
+    if let Some(dir) = matches.get_one::<PathBuf>("runtimes-dir") {
+        config.runtimes_dir = Some(dir.clone());
+    }
+    if matches.get_flag("offline") {
+        config.offline = true;
+    }
+    if let Some(secs) = matches.get_one::<u64>("timeout") {
+        config.download.connect_timeout = Duration::from_secs(*secs);
+        config.download.read_timeout = Duration::from_secs(*secs);
+    }
+    if let Some(retries) = matches.get_one::<u32>("retries") {
+        config.download.retries = *retries;
+    }
+    if let Some(max_downloads) = matches.get_one::<usize>("max-downloads") {
+        config.download.max_concurrent = *max_downloads;
+    }
+    Ok(config)
+}
+
+fn spawn_progress_renderer() -> (Progress, JoinHandle<()>) {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let handle = std::thread::spawn(move || render_progress(receiver));
+    (Progress::new(sender), handle)
+}
+
+/// Logs phase changes and draws a progress bar for downloads on stderr,
+/// until every sender is gone.
+fn render_progress(receiver: Receiver<ProgressEvent>) {
+    const BAR_WIDTH: usize = 30;
+    let interactive = std::io::stderr().is_terminal();
+    let mut state = ProgressState::default();
+    let mut bar_visible = false;
+    let mut last_draw: Option<Instant> = None;
+
+    for event in receiver {
+        let finished_file = matches!(event, ProgressEvent::FileFinished { .. });
+        if state.apply(event) {
+            if bar_visible {
+                eprintln!();
+                bar_visible = false;
+            }
+            info!("{}", state.phase);
+            continue;
         }
+        if !interactive
+            || (!finished_file
+                && last_draw.is_some_and(|t| t.elapsed() < Duration::from_millis(50)))
+        {
+            continue;
+        }
+        let line = match state.fraction() {
+            Some(fraction) => {
+                let filled = (fraction * BAR_WIDTH as f32) as usize;
+                format!(
+                    "[{}{}] {:>3}% {}",
+                    "#".repeat(filled),
+                    " ".repeat(BAR_WIDTH - filled),
+                    (fraction * 100.0) as u32,
+                    state.summary()
+                )
+            }
+            None => state.summary(),
+        };
+        eprint!("\r{}\x1b[K", line);
+        last_draw = Some(Instant::now());
+        // Finish the line once everything is done, so log output doesn't end up on the bar
+        bar_visible = state.fraction() != Some(1.0);
+        if !bar_visible {
+            eprintln!();
+        }
+    }
+    if bar_visible {
+        eprintln!();
     }
 }
 
-async fn parse(matches: ArgMatches) -> Result<InstallationResult, InstallerError> {
+async fn parse(
+    matches: ArgMatches,
+    progress: Progress,
+) -> Result<InstallationResult, InstallerError> {
+    let resolver = MetadataResolver::new();
+
     if let Some(matches) = matches.subcommand_matches("loader-versions") {
-        let versions = crate::net::meta::fetch_loader_versions().await?;
+        let versions = resolver.loader_versions().await?;
         let loader_type = get_loader_type(matches)?;
         let betas = matches.get_flag("show-betas");
 
@@ -126,7 +407,7 @@ async fn parse(matches: ArgMatches) -> Result<InstallationResult, InstallerError
             loader_type.get_localized_name(),
             versions
                 .get(&loader_type)
-                .and_then(|list| list.get(0))
+                .and_then(|list| list.first())
                 .map(|v| v.version.clone())
                 .unwrap_or("<not available>".to_owned())
         )?;
@@ -140,17 +421,177 @@ async fn parse(matches: ArgMatches) -> Result<InstallationResult, InstallerError
         return Ok(InstallationResult::NotInstalled);
     }
 
-    let minecraft_versions = crate::net::manifest::fetch_versions().await?;
-    let intermediary_versions = crate::net::meta::fetch_intermediary_versions().await?;
+    if let Some(matches) = matches.subcommand_matches("export") {
+        let source = matches.get_one::<PathBuf>("SOURCE").unwrap();
+        let name = match matches.get_one::<String>("name") {
+            Some(name) => name.clone(),
+            None => source
+                .canonicalize()?
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or("modpack".to_owned()),
+        };
+        let output = matches
+            .get_one::<PathBuf>("output")
+            .cloned()
+            .unwrap_or_else(|| PathBuf::from(name.clone() + ".mrpack"));
+        crate::actions::mrpack::export(
+            source,
+            matches.get_one::<String>("profile").map(String::as_str),
+            &output,
+            name,
+            matches.get_one::<String>("pack-version").unwrap().clone(),
+            &progress,
+        )
+        .await?;
+        return Ok(InstallationResult::NotInstalled);
+    }
+
+    if let Some(matches) = matches.subcommand_matches("launch-client") {
+        let location = matches.get_one::<PathBuf>("dir").unwrap().clone();
+        let options = LaunchOptions {
+            profile: matches.get_one::<String>("profile").cloned(),
+            minecraft_version: matches.get_one::<String>("minecraft-version").cloned(),
+            loader_type: match matches.contains_id("loader-type") {
+                true => Some(get_loader_type(matches)?),
+                false => None,
+            },
+            username: matches.get_one::<String>("username").unwrap().clone(),
+            java: matches.get_one::<PathBuf>("java").cloned(),
+            java_args: matches.get_one::<String>("java-args").cloned(),
+            game_dir: matches.get_one::<PathBuf>("game-dir").cloned(),
+            print_command: matches.get_flag("print-command"),
+        };
+        crate::actions::launch::launch_client(location, options, &progress).await?;
+        return Ok(InstallationResult::NotInstalled);
+    }
+
+    if let Some(matches) = matches.subcommand_matches("installed") {
+        let paths = |id: &str| {
+            matches
+                .get_many::<PathBuf>(id)
+                .map(|paths| paths.cloned().collect::<Vec<_>>())
+        };
+        let installations = crate::actions::inventory::scan(
+            &paths("dir").unwrap_or_default(),
+            &paths("instances").unwrap_or_else(super::instance_locations),
+            &paths("server").unwrap_or_default(),
+        );
+        if matches.get_flag("json") {
+            writeln!(
+                std::io::stdout(),
+                "{}",
+                serde_json::to_string_pretty(&installations)?
+            )?;
+        } else {
+            print_installations(&installations)?;
+        }
+        return Ok(InstallationResult::NotInstalled);
+    }
+
+    if let Some(matches) = matches.subcommand_matches("uninstall") {
+        let (side, matches) = matches.subcommand().unwrap();
+        let location = matches.get_one::<PathBuf>("dir").unwrap().clone();
+        let loader_type = match matches.contains_id("loader-type") {
+            true => Some(get_loader_type(matches)?),
+            false => None,
+        };
+        let minecraft_version = matches
+            .try_get_one::<String>("minecraft-version")
+            .ok()
+            .flatten()
+            .cloned();
+        match side {
+            "client" => {
+                crate::actions::uninstall::uninstall_client(
+                    location,
+                    minecraft_version,
+                    loader_type,
+                    progress,
+                )
+                .await?
+            }
+            "mmc" => {
+                crate::actions::uninstall::uninstall_mmc(
+                    location,
+                    minecraft_version,
+                    loader_type,
+                    &resolver,
+                    progress,
+                )
+                .await?
+            }
+            _ => {
+                crate::actions::uninstall::uninstall_server(
+                    location,
+                    loader_type,
+                    &resolver,
+                    progress,
+                )
+                .await?
+            }
+        }
+        return Ok(InstallationResult::NotInstalled);
+    }
+
+    if let Some(matches) = matches.subcommand_matches("update") {
+        let (side, matches) = matches.subcommand().unwrap();
+        let location = matches.get_one::<PathBuf>("dir").unwrap().clone();
+        let target = UpdateTarget {
+            minecraft_version: matches.get_one::<String>("minecraft-version").cloned(),
+            loader_type: match matches.contains_id("loader-type") {
+                true => Some(get_loader_type(matches)?),
+                false => None,
+            },
+            loader_version: matches.get_one::<String>("loader-version").unwrap().clone(),
+        };
+        match side {
+            "client" => {
+                crate::actions::update::update_client(location, target, &resolver, progress).await?
+            }
+            "mmc" => {
+                crate::actions::update::update_mmc(location, target, &resolver, progress).await?
+            }
+            _ => {
+                crate::actions::update::update_server(location, target, &resolver, progress).await?
+            }
+        }
+        return Ok(InstallationResult::NotInstalled);
+    }
+
+    let modpack = match matches
+        .subcommand()
+        .and_then(|(_, matches)| matches.try_get_one::<PathBuf>("mrpack").ok().flatten())
+    {
+        Some(path) => Some(Modpack::open(path)?),
+        None => None,
+    };
+
+    let intermediary_gen = match &modpack {
+        Some(modpack) => modpack.intermediary_gen()?,
+        None => matches
+            .subcommand()
+            .and_then(|(_, matches)| {
+                matches
+                    .try_get_one::<IntermediaryGen>("intermediary-gen")
+                    .ok()
+                    .flatten()
+            })
+            .copied()
+            .unwrap_or_default(),
+    };
+
+    let minecraft_versions = resolver.versions().await?;
+    let intermediary_versions = resolver.intermediary_versions(intermediary_gen).await?;
 
     let mut available_minecraft_versions = Vec::new();
 
-    for version in minecraft_versions.versions {
+    for version in &minecraft_versions.versions {
         if intermediary_versions.contains_key(&version.id)
             || intermediary_versions.contains_key(&(version.id.clone() + "-client"))
             || intermediary_versions.contains_key(&(version.id.clone() + "-server"))
         {
-            available_minecraft_versions.push(version);
+            available_minecraft_versions.push(version.clone());
         }
     }
 
@@ -179,95 +620,189 @@ async fn parse(matches: ArgMatches) -> Result<InstallationResult, InstallerError
         return Ok(InstallationResult::NotInstalled);
     }
 
-    let loader_versions = crate::net::meta::fetch_loader_versions().await?;
+    let loader_versions = resolver.loader_versions().await?;
 
     if let Some(matches) = matches.subcommand_matches("client") {
-        let minecraft_version = get_minecraft_version(matches, available_minecraft_versions)?;
-        let loader_type = get_loader_type(matches)?;
-        let loader_versions = loader_versions.get(&loader_type).unwrap();
-        let loader_version = get_loader_version(matches, loader_versions)?;
+        let minecraft_version =
+            get_minecraft_version(matches, modpack.as_ref(), available_minecraft_versions)?;
+        let (loader_type, loader_version) = get_loader(matches, modpack.as_ref(), loader_versions)?;
         let location = matches.get_one::<PathBuf>("dir").unwrap().clone();
-        let create_profile = matches.get_flag("generate-profile");
+        let profile = matches
+            .get_flag("generate-profile")
+            .then(|| ProfileOptions {
+                name: matches.get_one::<String>("profile-name").cloned(),
+                java_args: matches.get_one::<String>("java-args").cloned(),
+                game_dir: matches.get_one::<PathBuf>("game-dir").cloned(),
+                icon: matches.get_one::<PathBuf>("icon").cloned(),
+                java_dir: matches.get_one::<PathBuf>("java-dir").cloned(),
+            });
+        let mods = get_mod_selection(matches, modpack);
+        let result = installation_result(&mods);
+        let mut plan = new_plan(matches);
         crate::actions::client::install(
             minecraft_version,
             loader_type,
             loader_version,
+            intermediary_gen,
             location,
-            create_profile,
+            profile,
+            matches.get_flag("offline-ready"),
+            mods,
+            &resolver,
+            progress,
+            &mut plan,
         )
         .await?;
-        return Ok(InstallationResult::Installed);
+        return finish_plan(matches, &plan, result);
     }
 
     if let Some(matches) = matches.subcommand_matches("server") {
-        let minecraft_version = get_minecraft_version(matches, available_minecraft_versions)?;
-        let loader_type = get_loader_type(matches)?;
-        let loader_versions = loader_versions.get(&loader_type).unwrap();
-        let loader_version = get_loader_version(matches, loader_versions)?;
+        let minecraft_version =
+            get_minecraft_version(matches, modpack.as_ref(), available_minecraft_versions)?;
+        let (loader_type, loader_version) = get_loader(matches, modpack.as_ref(), loader_versions)?;
         let location = matches.get_one::<PathBuf>("dir").unwrap().clone();
-        if let Some(matches) = matches.subcommand_matches("run") {
+        if let Some(run_matches) = matches.subcommand_matches("run") {
+            if matches.get_flag("dry-run") {
+                return Err(InstallerError::other(
+                    "--dry-run can't be combined with run",
+                ));
+            }
+            let mods = get_mod_selection(matches, modpack);
+            let result = installation_result(&mods);
+            let matches = run_matches;
             let java = matches.get_one::<PathBuf>("java");
             let run_args = matches.get_one::<String>("args");
             crate::actions::server::install_and_run(
                 minecraft_version,
                 loader_type,
                 loader_version,
+                intermediary_gen,
                 location,
                 java,
                 run_args.map(|s| s.split(" ")),
+                mods,
+                &resolver,
+                progress,
             )
             .await?;
-            return Ok(InstallationResult::Installed);
+            return Ok(result);
         }
+        let mods = get_mod_selection(matches, modpack);
+        let result = installation_result(&mods);
+        let mut plan = new_plan(matches);
         crate::actions::server::install(
             minecraft_version,
             loader_type,
             loader_version,
+            intermediary_gen,
             location,
             matches.get_flag("download-minecraft"),
+            mods,
+            &resolver,
+            progress,
+            &mut plan,
         )
         .await?;
-        return Ok(InstallationResult::Installed);
+        return finish_plan(matches, &plan, result);
     }
 
     if let Some(matches) = matches.subcommand_matches("mmc") {
-        let minecraft_version = get_minecraft_version(matches, available_minecraft_versions)?;
-        let loader_type = get_loader_type(matches)?;
-        let loader_versions = loader_versions.get(&loader_type).unwrap();
-        let loader_version = get_loader_version(matches, loader_versions)?;
+        let minecraft_version =
+            get_minecraft_version(matches, modpack.as_ref(), available_minecraft_versions)?;
+        let (loader_type, loader_version) = get_loader(matches, modpack.as_ref(), loader_versions)?;
         let output_dir = matches.get_one::<PathBuf>("dir").unwrap().clone();
-        let copy_profile_path = matches
-            .get_one::<bool>("copy-profile-path")
-            .unwrap()
-            .clone();
-        let generate_zip = matches.get_one::<bool>("generate-zip").unwrap().clone();
+        let copy_profile_path = *matches.get_one::<bool>("copy-profile-path").unwrap();
+        let generate_zip = *matches.get_one::<bool>("generate-zip").unwrap();
+        let mods = get_mod_selection(matches, modpack);
+        let result = installation_result(&mods);
+        let mut plan = new_plan(matches);
         crate::actions::mmc_pack::install(
             minecraft_version,
             loader_type,
             loader_version,
+            intermediary_gen,
             output_dir,
             copy_profile_path,
             generate_zip,
+            mods,
+            &resolver,
+            progress,
+            &mut plan,
         )
         .await?;
-        return Ok(InstallationResult::Installed);
+        return finish_plan(matches, &plan, result);
     }
 
     Ok(InstallationResult::NotInstalled)
 }
 
+fn print_installations(installations: &[Installation]) -> Result<(), InstallerError> {
+    let mut out = std::io::stdout().lock();
+    if installations.is_empty() {
+        writeln!(out, "No Ornithe installations found")?;
+        return Ok(());
+    }
+    let rows = installations
+        .iter()
+        .map(|installation| {
+            [
+                installation.kind.name().to_owned(),
+                installation.minecraft_version.clone(),
+                installation.loader_type.clone(),
+                installation.loader_version.clone(),
+                match &installation.intermediary_version {
+                    Some(version) => {
+                        format!("{} (gen {})", version, installation.intermediary_generation)
+                    }
+                    None => format!("gen {}", installation.intermediary_generation),
+                },
+                installation.path.display().to_string(),
+            ]
+        })
+        .collect::<Vec<_>>();
+    let header = [
+        "Type",
+        "Minecraft",
+        "Loader",
+        "Loader version",
+        "Intermediary",
+        "Location",
+    ]
+    .map(str::to_owned);
+    let mut widths = header.clone().map(|title| title.len());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    for row in std::iter::once(&header).chain(&rows) {
+        let line = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:width$}", cell))
+            .collect::<Vec<_>>()
+            .join("  ");
+        writeln!(out, "{}", line.trim_end())?;
+    }
+    Ok(())
+}
+
 fn get_minecraft_version(
     matches: &ArgMatches,
+    modpack: Option<&Modpack>,
     versions: Vec<MinecraftVersion>,
 ) -> Result<MinecraftVersion, InstallerError> {
-    let minecraft_version_arg = matches.get_one::<String>("minecraft-version").unwrap();
+    let minecraft_version_arg = match modpack {
+        Some(modpack) => modpack.minecraft_version()?,
+        None => matches.get_one::<String>("minecraft-version").unwrap(),
+    };
 
     for version in versions {
         if version.id == *minecraft_version_arg {
             return Ok(version);
         }
     }
-    Err(InstallerError(
+    Err(InstallerError::NotFound(
         "Could not find Minecraft version ".to_owned()
             + minecraft_version_arg
             + " among supported versions!",
@@ -275,25 +810,50 @@ fn get_minecraft_version(
 }
 
 fn get_loader_type(matches: &ArgMatches) -> Result<LoaderType, InstallerError> {
-    Ok(
-        match matches.get_one::<String>("loader-type").unwrap().as_str() {
-            "quilt" => crate::net::meta::LoaderType::Quilt,
-            "fabric" => crate::net::meta::LoaderType::Fabric,
-            &_ => {
-                return Err(InstallerError("Unsupported loader type!".to_owned()));
-            }
-        },
-    )
+    let id = matches.get_one::<String>("loader-type").unwrap();
+    let loaders = &crate::config::get().loaders;
+    loaders.get(id).cloned().ok_or_else(|| {
+        InstallerError::NotFound(format!(
+            "Unsupported loader type {}! Available loaders: {}",
+            id,
+            loaders.ids().join(", ")
+        ))
+    })
 }
 
-fn get_loader_version(
+/// The loader and loader version to install, the ones required by the modpack
+/// if it names a loader.
+fn get_loader(
     matches: &ArgMatches,
+    modpack: Option<&Modpack>,
+    loader_versions: &HashMap<LoaderType, Vec<LoaderVersion>>,
+) -> Result<(LoaderType, LoaderVersion), InstallerError> {
+    let (loader_type, version_arg) = match modpack.map(Modpack::loader).transpose()?.flatten() {
+        Some((loader_type, version)) => (loader_type, version),
+        None => (
+            get_loader_type(matches)?,
+            matches
+                .get_one::<String>("loader-version")
+                .unwrap()
+                .as_str(),
+        ),
+    };
+    let versions = loader_versions.get(&loader_type).ok_or_else(|| {
+        InstallerError::NotFound(format!(
+            "No versions available for {} Loader",
+            loader_type.get_localized_name()
+        ))
+    })?;
+    let loader_version = get_loader_version(version_arg, versions)?;
+    Ok((loader_type, loader_version))
+}
+
+fn get_loader_version(
+    arg: &str,
     versions: &Vec<LoaderVersion>,
 ) -> Result<LoaderVersion, InstallerError> {
-    let arg = matches.get_one::<String>("loader-version").unwrap();
-
-    if *arg == "latest" {
-        return versions.get(0).map(|v| v.clone()).ok_or(InstallerError(
+    if arg == "latest" {
+        return versions.first().cloned().ok_or(InstallerError::NotFound(
             "Failed to find loader version in list".to_owned(),
         ));
     }
@@ -304,19 +864,99 @@ fn get_loader_version(
         }
     }
 
-    Err(InstallerError(
+    Err(InstallerError::NotFound(
         "Could not find loader version: ".to_owned() + arg,
     ))
 }
 
+fn get_mod_selection(matches: &ArgMatches, modpack: Option<Modpack>) -> ModSelection {
+    ModSelection {
+        osl: matches.get_one::<OslSource>("osl").copied(),
+        modpack,
+        mods: matches
+            .get_many::<String>("mod")
+            .map(|mods| mods.cloned().collect())
+            .unwrap_or_default(),
+    }
+}
+
+fn new_plan(matches: &ArgMatches) -> Plan {
+    if matches.get_flag("dry-run") {
+        Plan::dry_run()
+    } else {
+        Plan::default()
+    }
+}
+
+/// Prints the changes of a dry run, which installed nothing.
+fn finish_plan(
+    matches: &ArgMatches,
+    plan: &Plan,
+    result: InstallationResult,
+) -> Result<InstallationResult, InstallerError> {
+    if !plan.is_dry_run() {
+        return Ok(result);
+    }
+    if matches.get_flag("json") {
+        writeln!(std::io::stdout(), "{}", serde_json::to_string_pretty(plan)?)?;
+    } else {
+        write!(std::io::stdout(), "{}", plan)?;
+    }
+    Ok(InstallationResult::NotInstalled)
+}
+
+fn installation_result(mods: &ModSelection) -> InstallationResult {
+    match mods.osl {
+        Some(_) => InstallationResult::InstalledWithOsl,
+        None => InstallationResult::Installed,
+    }
+}
+
+fn intermediary_gen_arg() -> Arg {
+    arg!(--"intermediary-gen" <GEN> "Intermediary generation to use, e.g. 1 or 2")
+        .default_value("1")
+        .value_parser(value_parser!(IntermediaryGen))
+}
+
+fn uninstall_arguments(command: Command) -> Command {
+    command
+        .arg(arg!(-m --"minecraft-version" <VERSION> "Only remove installations of this Minecraft version"))
+        .arg(arg!(--"loader-type" <TYPE> "Only remove installations of this loader type"))
+}
+
+fn update_arguments(command: Command) -> Command {
+    command
+        .arg(arg!(-m --"minecraft-version" <VERSION> "Only update installations of this Minecraft version"))
+        .arg(arg!(--"loader-type" <TYPE> "Only update installations of this loader type"))
+        .arg(arg!(--"loader-version" <VERSION> "Loader version to update to").default_value("latest"))
+}
+
 fn add_arguments(command: Command) -> Command {
     command
-        .arg(arg!(-m --"minecraft-version" <VERSION> "Minecraft version to use").required(true))
         .arg(
-            arg!(--"loader-type" <TYPE> "Loader type to use")
-                .default_value("fabric")
-                .ignore_case(true)
-                .value_parser(["fabric", "quilt"]),
+            arg!(-m --"minecraft-version" <VERSION> "Minecraft version to use")
+                .required_unless_present("mrpack"),
+        )
+        .arg(
+            arg!(--"loader-type" <TYPE> "Loader type to use, e.g. fabric or quilt")
+                .default_value("fabric"),
         )
         .arg(arg!(--"loader-version" <VERSION> "Loader version to use").default_value("latest"))
+        .arg(intermediary_gen_arg())
+        .arg(
+            arg!(--osl [SOURCE] "Also install the Ornithe Standard Libraries, from modrinth or maven")
+                .num_args(0..=1)
+                .default_missing_value("modrinth")
+                .value_parser(value_parser!(OslSource)),
+        )
+        .arg(
+            arg!(--"mod" <ID> "Modrinth project slug or id of a mod to install, can be repeated")
+                .action(ArgAction::Append),
+        )
+        .arg(
+            arg!(--mrpack <FILE> "Modrinth modpack to install, its game and loader versions are used")
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(arg!(--"dry-run" "Print the changes the installation would make without making them"))
+        .arg(arg!(--json "Print the changes of a dry run as json").requires("dry-run"))
 }
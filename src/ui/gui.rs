@@ -1,22 +1,34 @@
 use std::{
     collections::HashMap,
-    path::Path,
-    sync::mpsc::{Receiver, Sender},
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        mpsc::{Receiver, Sender},
+    },
+    time::Duration,
 };
 
-use egui::{Button, ComboBox, RichText, Sense, Theme, Vec2};
+use egui::{Button, ComboBox, ProgressBar, RichText, Sense, Theme, Vec2};
 use egui_dropdown::DropDownBox;
 use log::{error, info};
 use rfd::{AsyncFileDialog, AsyncMessageDialog, MessageButtons, MessageDialogResult};
 use tokio::task::JoinHandle;
 
 use crate::{
+    actions::{
+        client::ProfileOptions,
+        inventory::{self, Installation},
+        mods::{ModSelection, OslSource},
+        plan::Plan,
+    },
     errors::InstallerError,
     net::{
-        self,
+        loaders::LoaderType,
         manifest::MinecraftVersion,
-        meta::{LoaderType, LoaderVersion},
+        meta::{IntermediaryGen, LoaderVersion},
+        resolver::MetadataResolver,
     },
+    progress::{Progress, ProgressEvent, ProgressState},
 };
 
 use super::Mode;
@@ -26,8 +38,8 @@ pub async fn run() -> Result<(), InstallerError> {
 
     let res = create_window().await;
     if let Err(e) = res {
-        error!("{}", e.0);
-        display_dialog("Ornithe Installer Error", &e.0);
+        error!("{}", e.report());
+        display_dialog("Ornithe Installer Error", &describe_error(&e));
         return Err(e);
     }
 
@@ -35,11 +47,13 @@ pub async fn run() -> Result<(), InstallerError> {
 }
 
 async fn create_window() -> Result<(), InstallerError> {
+    crate::config::init(crate::config::Config::load(None)?);
+
     let data = eframe::icon_data::from_png_bytes(crate::ORNITHE_ICON_BYTES)
         .expect("The Ornithe Icon is a valid PNG file");
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
-            .with_inner_size([630.0, 490.0])
+            .with_inner_size([630.0, 560.0])
             .with_icon(data),
         renderer: eframe::Renderer::Wgpu,
         ..Default::default()
@@ -54,13 +68,36 @@ async fn create_window() -> Result<(), InstallerError> {
     Ok(())
 }
 
+/// Explains an error in terms of what the user can do about it.
+fn describe_error(error: &InstallerError) -> String {
+    let hint = match error {
+        InstallerError::Network { .. } => {
+            "Could not download the required files. Check your internet connection and try again."
+        }
+        InstallerError::Io { .. } => {
+            "Could not write the installation. Check that the selected location exists and is writable."
+        }
+        InstallerError::Parse { .. } => {
+            "Received data in an unexpected format. The installer may be outdated."
+        }
+        InstallerError::NotFound(_) => {
+            "Something required for the installation could not be found."
+        }
+        InstallerError::Conflict(_) => "The installation conflicts with existing files.",
+        InstallerError::Integrity(_) => "A downloaded file was corrupted. Please try again.",
+        InstallerError::Cancelled => "The installation was cancelled.",
+        InstallerError::Other { .. } => "Failed to install.",
+    };
+    format!("{}\n\nDetails: {}", hint, error.report())
+}
+
 fn display_dialog(title: &str, message: &str) {
     display_dialog_ext(title, message, MessageButtons::Ok, |_| {});
 }
 
 fn display_dialog_ext<F>(title: &str, message: &str, buttons: MessageButtons, after: F)
 where
-    F: FnOnce(MessageDialogResult) -> (),
+    F: FnOnce(MessageDialogResult),
     F: Send,
     F: 'static,
 {
@@ -76,11 +113,26 @@ where
     });
 }
 
+/// The text of an optional field, if it was filled in.
+fn non_empty(text: &str) -> Option<String> {
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_owned())
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+enum Tab {
+    Install,
+    /// The list of existing installations.
+    Installed,
+}
+
 struct App {
+    tab: Tab,
     mode: Mode,
     selected_minecraft_version: String,
     available_minecraft_versions: Vec<MinecraftVersion>,
-    available_intermediary_versions: Vec<String>,
+    available_intermediary_versions: HashMap<IntermediaryGen, Vec<String>>,
+    selected_intermediary_gen: IntermediaryGen,
     show_snapshots: bool,
     show_historical: bool,
     selected_loader_type: LoaderType,
@@ -88,18 +140,37 @@ struct App {
     available_loader_versions: HashMap<LoaderType, Vec<LoaderVersion>>,
     show_betas: bool,
     create_profile: bool,
+    /// Options of the generated launch profile, left empty for the defaults.
+    profile_name: String,
+    java_args: String,
+    profile_game_dir: String,
+    profile_icon: String,
+    java_dir: String,
+    /// Whether to also install the game files the launcher would download.
+    offline_ready: bool,
     client_install_location: String,
     mmc_output_location: String,
     server_install_location: String,
     copy_generated_location: bool,
     generate_zip: bool,
     download_minecraft_server: bool,
+    install_osl: bool,
+    /// Whether the running installation also installs the Ornithe Standard Libraries.
+    installing_osl: bool,
+    /// Whether the running task removes an installation.
+    uninstalling: bool,
+    uninstall_confirmation_channel: (Sender<bool>, Receiver<bool>),
     installation_task: Option<JoinHandle<Result<(), InstallerError>>>,
+    /// Installations shown in the installed tab, scanned when it is opened.
+    installations: Option<Vec<Installation>>,
     file_picker_channel: (
         Sender<Option<FilePickResult>>,
         Receiver<Option<FilePickResult>>,
     ),
     file_picker_open: bool,
+    progress_channel: (Sender<ProgressEvent>, Receiver<ProgressEvent>),
+    progress: ProgressState,
+    resolver: Arc<MetadataResolver>,
 }
 
 struct FilePickResult {
@@ -110,18 +181,20 @@ struct FilePickResult {
 impl App {
     async fn create() -> Result<App, InstallerError> {
         let mut available_minecraft_versions = Vec::new();
-        let mut available_intermediary_versions = Vec::new();
+        let mut available_intermediary_versions = HashMap::new();
         let mut available_loader_versions = HashMap::new();
+        let resolver = Arc::new(MetadataResolver::new());
 
         info!("Loading versions...");
-        if let Ok(versions) = net::manifest::fetch_versions().await {
-            for ele in versions.versions {
-                available_minecraft_versions.push(ele);
+        if let Ok(versions) = resolver.versions().await {
+            for ele in &versions.versions {
+                available_minecraft_versions.push(ele.clone());
             }
         }
-        if let Ok(versions) = net::meta::fetch_intermediary_versions().await {
-            for v in versions.keys() {
-                available_intermediary_versions.push(v.clone());
+        for intermediary_gen in IntermediaryGen::KNOWN {
+            if let Ok(versions) = resolver.intermediary_versions(intermediary_gen).await {
+                available_intermediary_versions
+                    .insert(intermediary_gen, versions.keys().cloned().collect());
             }
         }
         info!(
@@ -129,34 +202,52 @@ impl App {
             available_minecraft_versions.len()
         );
 
-        if let Ok(versions) = net::meta::fetch_loader_versions().await {
-            available_loader_versions = versions;
+        if let Ok(versions) = resolver.loader_versions().await {
+            available_loader_versions = versions.clone();
         }
 
+        let default_loader = crate::config::get().loaders.default_loader().clone();
         let app = App {
+            tab: Tab::Install,
             mode: Mode::Client,
             selected_minecraft_version: String::new(),
             available_minecraft_versions,
             available_intermediary_versions,
+            selected_intermediary_gen: IntermediaryGen::default(),
             show_snapshots: false,
             show_historical: false,
-            selected_loader_type: LoaderType::Fabric,
             selected_loader_version: available_loader_versions
-                .get(&LoaderType::Fabric)
-                .map(|v| v.get(0).unwrap().version.clone())
+                .get(&default_loader)
+                .and_then(|v| v.first())
+                .map(|v| v.version.clone())
                 .unwrap_or(String::new()),
+            selected_loader_type: default_loader,
             available_loader_versions,
             show_betas: false,
             create_profile: true,
+            profile_name: String::new(),
+            java_args: String::new(),
+            profile_game_dir: String::new(),
+            profile_icon: String::new(),
+            java_dir: String::new(),
+            offline_ready: false,
             client_install_location: super::dot_minecraft_location(),
             mmc_output_location: super::current_location(),
             server_install_location: super::server_location(),
             copy_generated_location: false,
             generate_zip: true,
             download_minecraft_server: true,
+            install_osl: false,
+            installing_osl: false,
+            uninstalling: false,
+            uninstall_confirmation_channel: std::sync::mpsc::channel(),
             file_picker_channel: std::sync::mpsc::channel(),
             file_picker_open: false,
             installation_task: None,
+            installations: None,
+            progress_channel: std::sync::mpsc::channel(),
+            progress: ProgressState::default(),
+            resolver,
         };
         Ok(app)
     }
@@ -178,18 +269,18 @@ impl App {
                 .pick_folder();
             self.file_picker_open = true;
             let sender = self.file_picker_channel.0.clone();
-            let mode = self.mode.clone();
+            let mode = self.mode;
             let ctx = ui.ctx().clone();
             tokio::spawn(async move {
                 let opt = picked.await;
                 let mut send = None;
-                if let Some(path) = opt {
-                    if let Some(path) = path.path().to_str() {
-                        send = Some(FilePickResult {
-                            mode,
-                            path: path.to_owned(),
-                        });
-                    }
+                if let Some(path) = opt
+                    && let Some(path) = path.path().to_str()
+                {
+                    send = Some(FilePickResult {
+                        mode,
+                        path: path.to_owned(),
+                    });
                 }
                 let _ = sender.send(send);
                 ctx.request_repaint();
@@ -208,6 +299,11 @@ impl App {
 
     fn add_minecraft_version(&mut self, ui: &mut egui::Ui) {
         ui.label("Minecraft Version");
+        let no_versions = Vec::new();
+        let intermediary_versions = self
+            .available_intermediary_versions
+            .get(&self.selected_intermediary_gen)
+            .unwrap_or(&no_versions);
         ui.horizontal(|ui| {
             ui.add(
                 DropDownBox::from_iter(
@@ -215,8 +311,8 @@ impl App {
                         .available_minecraft_versions
                         .iter()
                         .filter(|v| {
-                            self.available_intermediary_versions.contains(&v.id)
-                                || self.available_intermediary_versions.contains(
+                            intermediary_versions.contains(&v.id)
+                                || intermediary_versions.contains(
                                     &(v.id.clone()
                                         + "-"
                                         + match self.mode {
@@ -251,6 +347,20 @@ impl App {
             ui.checkbox(&mut self.show_snapshots, "Snapshots");
             ui.checkbox(&mut self.show_historical, "Historical Versions");
         });
+        ui.horizontal(|ui| {
+            ui.label("Intermediary: ");
+            ComboBox::from_id_salt("intermediary_gen")
+                .selected_text(self.selected_intermediary_gen.to_string())
+                .show_ui(ui, |ui| {
+                    for intermediary_gen in IntermediaryGen::KNOWN {
+                        ui.selectable_value(
+                            &mut self.selected_intermediary_gen,
+                            intermediary_gen,
+                            intermediary_gen.to_string(),
+                        );
+                    }
+                });
+        });
     }
 
     fn add_loader(&mut self, ui: &mut egui::Ui) {
@@ -262,21 +372,18 @@ impl App {
                     &self.selected_loader_type.get_localized_name()
                 ))
                 .show_ui(ui, |ui| {
-                    ui.selectable_value(
-                        &mut self.selected_loader_type,
-                        LoaderType::Fabric,
-                        "Fabric Loader",
-                    );
-                    ui.selectable_value(
-                        &mut self.selected_loader_type,
-                        LoaderType::Quilt,
-                        "Quilt Loader",
-                    );
+                    for loader in crate::config::get().loaders.all() {
+                        ui.selectable_value(
+                            &mut self.selected_loader_type,
+                            loader.clone(),
+                            format!("{} Loader", loader.get_localized_name()),
+                        );
+                    }
                 });
 
             ui.label("Version: ");
             ComboBox::from_id_salt("loader_version")
-                .selected_text(format!("{}", &self.selected_loader_version))
+                .selected_text(self.selected_loader_version.to_string())
                 .show_ui(ui, |ui| {
                     for ele in self
                         .available_loader_versions
@@ -293,13 +400,13 @@ impl App {
                     }
                 });
             let checkbox_response = ui.checkbox(&mut self.show_betas, "Show Betas");
-            if !self
+            if self
                 .available_loader_versions
                 .get(&self.selected_loader_type)
                 .unwrap()
                 .iter()
                 .find(|v| v.version == self.selected_loader_version)
-                .is_some()
+                .is_none()
                 || checkbox_response.clicked()
             {
                 self.selected_loader_version = self
@@ -331,18 +438,41 @@ impl App {
                 .find(|v| v.version == self.selected_loader_version)
                 .unwrap()
                 .clone();
+            let intermediary_gen = self.selected_intermediary_gen;
+            let mods = ModSelection {
+                osl: self.install_osl.then_some(OslSource::Modrinth),
+                ..Default::default()
+            };
+            self.installing_osl = self.install_osl;
+            self.uninstalling = false;
+            let progress = Progress::new(self.progress_channel.0.clone());
+            let resolver = self.resolver.clone();
+            self.progress = ProgressState::default();
             match self.mode {
                 Mode::Client => {
                     let loader_type = self.selected_loader_type.clone();
                     let location = Path::new(&self.client_install_location).to_path_buf();
-                    let create_profile = self.create_profile;
+                    let profile = self.create_profile.then(|| ProfileOptions {
+                        name: non_empty(&self.profile_name),
+                        java_args: non_empty(&self.java_args),
+                        game_dir: non_empty(&self.profile_game_dir).map(PathBuf::from),
+                        icon: non_empty(&self.profile_icon).map(PathBuf::from),
+                        java_dir: non_empty(&self.java_dir).map(PathBuf::from),
+                    });
+                    let offline_ready = self.offline_ready;
                     let handle = tokio::spawn(async move {
                         crate::actions::client::install(
                             selected_version,
                             loader_type,
                             loader_version,
+                            intermediary_gen,
                             location,
-                            create_profile,
+                            profile,
+                            offline_ready,
+                            mods,
+                            &resolver,
+                            progress,
+                            &mut Plan::default(),
                         )
                         .await
                     });
@@ -357,8 +487,13 @@ impl App {
                             selected_version,
                             loader_type,
                             loader_version,
+                            intermediary_gen,
                             location,
                             download_server,
+                            mods,
+                            &resolver,
+                            progress,
+                            &mut Plan::default(),
                         )
                         .await
                     }));
@@ -373,9 +508,14 @@ impl App {
                             selected_version,
                             loader_type,
                             loader_version,
+                            intermediary_gen,
                             location,
                             copy_profile_path,
                             generate_zip,
+                            mods,
+                            &resolver,
+                            progress,
+                            &mut Plan::default(),
                         )
                         .await
                     });
@@ -390,41 +530,277 @@ impl App {
         }
     }
 
-    fn monitor_installation(&mut self) {
-        if let Some(task) = &self.installation_task {
-            if task.is_finished() {
-                let handle = self.installation_task.take().unwrap();
+    fn confirm_uninstall(&mut self, ctx: &egui::Context) {
+        // Server directories only hold a single installation
+        let version = match self.mode == Mode::Server || self.selected_minecraft_version.is_empty()
+        {
+            true => "all Minecraft versions".to_owned(),
+            false => "Minecraft ".to_owned() + &self.selected_minecraft_version,
+        };
+        let location = match self.mode {
+            Mode::Client => &self.client_install_location,
+            Mode::Server => &self.server_install_location,
+            Mode::MMC => &self.mmc_output_location,
+        };
+        let sender = self.uninstall_confirmation_channel.0.clone();
+        let ctx = ctx.clone();
+        display_dialog_ext(
+            "Uninstall Ornithe",
+            &format!(
+                "Remove the {} Loader installation for {} from {}?\nWorlds, configs and mods are kept.",
+                self.selected_loader_type.get_localized_name(),
+                version,
+                location
+            ),
+            MessageButtons::YesNo,
+            move |res| {
+                let _ = sender.send(res == MessageDialogResult::Yes);
+                ctx.request_repaint();
+            },
+        );
+    }
+
+    fn run_uninstall(&mut self) {
+        let minecraft_version =
+            Some(self.selected_minecraft_version.clone()).filter(|version| !version.is_empty());
+        let loader_type = Some(self.selected_loader_type.clone());
+        let progress = Progress::new(self.progress_channel.0.clone());
+        let resolver = self.resolver.clone();
+        self.progress = ProgressState::default();
+        self.uninstalling = true;
+        self.installation_task = Some(match self.mode {
+            Mode::Client => {
+                let location = Path::new(&self.client_install_location).to_path_buf();
+                tokio::spawn(crate::actions::uninstall::uninstall_client(
+                    location,
+                    minecraft_version,
+                    loader_type,
+                    progress,
+                ))
+            }
+            Mode::Server => {
+                let location = Path::new(&self.server_install_location).to_path_buf();
                 tokio::spawn(async move {
-                    match handle.await.unwrap() {
-                        Err(e) => {
-                            error!("{}", e.0);
-                            display_dialog(
-                                "Installation Failed",
-                                &("Failed to install: ".to_owned() + &e.0),
-                            )
-                        }
-                        Ok(_) => display_dialog_ext(
-                            "Installation Successful",
-                            "Ornithe has been successfully installed.\nMost mods require that you also download the Ornithe Standard Libraries mod and place it in your mods folder.\nWould you like to open OSL's modrinth page now?",
-                            MessageButtons::YesNo,
-                            |res| {
-                                if res == MessageDialogResult::Yes {
-                                    if webbrowser::open(crate::OSL_MODRINTH_URL).is_err() {
-                                        display_dialog("Failed to open modrinth", &("Failed to open modrinth page for Ornithe Standard Libraries.\nYou can find it at ".to_owned()+crate::OSL_MODRINTH_URL).as_str());
-                                    }
-                                }
-                            },
-                        ),
+                    crate::actions::uninstall::uninstall_server(
+                        location,
+                        loader_type,
+                        &resolver,
+                        progress,
+                    )
+                    .await
+                })
+            }
+            Mode::MMC => {
+                let location = Path::new(&self.mmc_output_location).to_path_buf();
+                tokio::spawn(async move {
+                    crate::actions::uninstall::uninstall_mmc(
+                        location,
+                        minecraft_version,
+                        loader_type,
+                        &resolver,
+                        progress,
+                    )
+                    .await
+                })
+            }
+        });
+    }
+
+    fn add_progress(&mut self, ui: &mut egui::Ui) {
+        while let Ok(event) = self.progress_channel.1.try_recv() {
+            if self.progress.apply(event) {
+                info!("{}", self.progress.phase);
+            }
+        }
+        if self.installation_task.is_none() {
+            return;
+        }
+
+        ui.label(&self.progress.phase);
+        let mut bar =
+            ProgressBar::new(self.progress.fraction().unwrap_or(0.0)).text(self.progress.summary());
+        if self.progress.fraction().is_none() {
+            bar = bar.animate(true);
+        }
+        ui.add(bar);
+        ui.ctx().request_repaint_after(Duration::from_millis(100));
+    }
+
+    fn monitor_installation(&mut self) {
+        if let Some(task) = &self.installation_task
+            && task.is_finished()
+        {
+            let handle = self.installation_task.take().unwrap();
+            let installed_osl = self.installing_osl;
+            let uninstalled = self.uninstalling;
+            tokio::spawn(async move {
+                match handle.await.map_err(InstallerError::from).and_then(|r| r) {
+                    Err(InstallerError::Cancelled) => info!("Installation cancelled"),
+                    Err(e) if uninstalled => {
+                        error!("{}", e.report());
+                        display_dialog("Uninstall Failed", &describe_error(&e))
                     }
-                });
+                    Ok(_) if uninstalled => display_dialog(
+                        "Uninstall Successful",
+                        "Ornithe has been successfully removed.",
+                    ),
+                    Err(e) => {
+                        error!("{}", e.report());
+                        display_dialog("Installation Failed", &describe_error(&e))
+                    }
+                    Ok(_) if installed_osl => display_dialog(
+                        "Installation Successful",
+                        "Ornithe and the Ornithe Standard Libraries have been successfully installed.",
+                    ),
+                    Ok(_) => display_dialog_ext(
+                        "Installation Successful",
+                        "Ornithe has been successfully installed.\nMost mods require that you also download the Ornithe Standard Libraries mod and place it in your mods folder.\nWould you like to open OSL's modrinth page now?",
+                        MessageButtons::YesNo,
+                        |res| {
+                            if res == MessageDialogResult::Yes
+                                && webbrowser::open(crate::OSL_MODRINTH_URL).is_err()
+                            {
+                                display_dialog("Failed to open modrinth", ("Failed to open modrinth page for Ornithe Standard Libraries.\nYou can find it at ".to_owned()+crate::OSL_MODRINTH_URL).as_str());
+                            }
+                        },
+                    ),
+                }
+            });
+        }
+    }
+
+    fn add_install_tab(
+        &mut self,
+        ctx: &egui::Context,
+        frame: &mut eframe::Frame,
+        ui: &mut egui::Ui,
+    ) {
+        ui.vertical(|ui| {
+            ui.add_space(15.0);
+
+            self.add_environment_options(ui);
+
+            ui.add_space(15.0);
+            self.add_minecraft_version(ui);
+            ui.add_space(15.0);
+            self.add_loader(ui);
+
+            ui.add_space(15.0);
+            ui.label(if self.mode == Mode::MMC && self.generate_zip {
+                "Output Location"
+            } else {
+                "Install Location"
+            });
+            ui.horizontal(|ui| self.add_location_picker(frame, ui));
+        });
+
+        ui.add_space(15.0);
+        self.add_additional_options(ui);
+
+        ui.add_space(15.0);
+        ui.vertical_centered(|ui| {
+            let mut install_button =
+                Button::new(RichText::new("Install").heading()).min_size(Vec2::new(100.0, 0.0));
+            if self.installation_task.is_some() {
+                install_button = install_button.sense(Sense::empty());
+            }
+            if ui.add(install_button).clicked() {
+                self.run_installation();
+            }
+            let mut uninstall_button = Button::new("Uninstall");
+            if self.installation_task.is_some() {
+                uninstall_button = uninstall_button.sense(Sense::empty());
+            }
+            if ui.add(uninstall_button).clicked() {
+                self.confirm_uninstall(ctx);
             }
+        });
+    }
+
+    fn add_installed_tab(&mut self, ui: &mut egui::Ui) {
+        let installations = self.installations.get_or_insert_with(|| {
+            let mut instance_dirs = super::instance_locations();
+            let mmc_location = PathBuf::from(&self.mmc_output_location);
+            if !instance_dirs.contains(&mmc_location) {
+                instance_dirs.push(mmc_location);
+            }
+            inventory::scan(
+                &[PathBuf::from(&self.client_install_location)],
+                &instance_dirs,
+                &[PathBuf::from(&self.server_install_location)],
+            )
+        });
+
+        ui.add_space(15.0);
+        if installations.is_empty() {
+            ui.label("No Ornithe installations found.");
+        } else {
+            egui::ScrollArea::both().max_height(300.0).show(ui, |ui| {
+                egui::Grid::new("installations")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for title in ["Type", "Minecraft", "Loader", "Intermediary", "Location"] {
+                            ui.strong(title);
+                        }
+                        ui.end_row();
+                        for installation in installations.iter() {
+                            ui.label(installation.kind.name());
+                            ui.label(&installation.minecraft_version);
+                            ui.label(format!(
+                                "{} {}",
+                                installation.loader_type, installation.loader_version
+                            ));
+                            ui.label(match &installation.intermediary_version {
+                                Some(version) => format!(
+                                    "{} (Gen {})",
+                                    version, installation.intermediary_generation
+                                ),
+                                None => format!("Gen {}", installation.intermediary_generation),
+                            });
+                            ui.label(installation.path.display().to_string());
+                            ui.end_row();
+                        }
+                    });
+            });
         }
+
+        ui.add_space(15.0);
+        ui.vertical_centered(|ui| {
+            if ui.button("Refresh").clicked() {
+                self.installations = None;
+            }
+        });
     }
 
     fn add_additional_options(&mut self, ui: &mut egui::Ui) {
         match self.mode {
             Mode::Client => {
                 ui.checkbox(&mut self.create_profile, "Generate Profile");
+                if self.create_profile {
+                    egui::CollapsingHeader::new("Profile Options").show(ui, |ui| {
+                        egui::Grid::new("profile_options").show(ui, |ui| {
+                            for (label, value, hint) in [
+                                ("Name", &mut self.profile_name, "Ornithe (Loader) Version"),
+                                ("Java Arguments", &mut self.java_args, "-Xmx2G"),
+                                (
+                                    "Game Directory",
+                                    &mut self.profile_game_dir,
+                                    "Install Location",
+                                ),
+                                ("Icon (PNG)", &mut self.profile_icon, "Ornithe logo"),
+                                ("Java Executable", &mut self.java_dir, "Launcher default"),
+                            ] {
+                                ui.label(label);
+                                ui.add(egui::TextEdit::singleline(value).hint_text(hint));
+                                ui.end_row();
+                            }
+                        });
+                    });
+                }
+                ui.checkbox(
+                    &mut self.offline_ready,
+                    "Download Game Files for Offline Use",
+                );
             }
             Mode::Server => {
                 ui.checkbox(
@@ -443,6 +819,7 @@ impl App {
                 });
             }
         }
+        ui.checkbox(&mut self.install_osl, "Install Ornithe Standard Libraries");
     }
 }
 
@@ -451,6 +828,10 @@ impl eframe::App for App {
         ctx.set_zoom_factor(1.5);
         ctx.options_mut(|opt| opt.fallback_theme = Theme::Light);
 
+        if let Ok(true) = self.uninstall_confirmation_channel.1.try_recv() {
+            self.run_uninstall();
+        }
+
         if let Ok(result) = self.file_picker_channel.1.try_recv() {
             self.file_picker_open = false;
             if let Some(result) = result {
@@ -467,39 +848,23 @@ impl eframe::App for App {
                 ui.vertical_centered(|ui| {
                     ui.heading("Ornithe Installer");
                 });
-                ui.vertical(|ui| {
-                    ui.add_space(15.0);
-
-                    self.add_environment_options(ui);
-
-                    ui.add_space(15.0);
-                    self.add_minecraft_version(ui);
-                    ui.add_space(15.0);
-                    self.add_loader(ui);
-
-                    ui.add_space(15.0);
-                    ui.label(if self.mode == Mode::MMC && self.generate_zip {
-                        "Output Location"
-                    } else {
-                        "Install Location"
-                    });
-                    ui.horizontal(|ui| self.add_location_picker(frame, ui));
-                });
-
-                ui.add_space(15.0);
-                self.add_additional_options(ui);
-
-                ui.add_space(15.0);
-                ui.vertical_centered(|ui| {
-                    let mut install_button = Button::new(RichText::new("Install").heading())
-                        .min_size(Vec2::new(100.0, 0.0));
-                    if self.installation_task.is_some() {
-                        install_button = install_button.sense(Sense::empty());
-                    }
-                    if ui.add(install_button).clicked() {
-                        self.run_installation();
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut self.tab, Tab::Install, "Install");
+                    if ui
+                        .selectable_value(&mut self.tab, Tab::Installed, "Installed")
+                        .clicked()
+                    {
+                        // Rescan, the locations or installations may have changed
+                        self.installations = None;
                     }
                 });
+                match self.tab {
+                    Tab::Install => self.add_install_tab(ctx, frame, ui),
+                    Tab::Installed => self.add_installed_tab(ui),
+                }
+
+                ui.add_space(10.0);
+                self.add_progress(ui);
             });
         });
 
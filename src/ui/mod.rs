@@ -3,6 +3,7 @@ use std::path::PathBuf;
 pub mod cli;
 pub mod gui;
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(PartialEq, Clone, Copy, Debug)]
 pub enum Mode {
     Client,
@@ -10,7 +11,7 @@ pub enum Mode {
     MMC,
 }
 
-fn home_dir() -> Option<PathBuf> {
+pub fn home_dir() -> Option<PathBuf> {
     #[allow(deprecated)]
     std::env::home_dir()
 }
@@ -27,7 +28,7 @@ fn location(minecraft_path: Option<PathBuf>, default: &str) -> String {
     path.to_str().unwrap_or(default).to_owned()
 }
 
-#[cfg(all(any(unix), not(target_os = "macos")))]
+#[cfg(all(unix, not(target_os = "macos")))]
 pub fn dot_minecraft_location() -> String {
     location(home_dir().map(|p| p.join(".minecraft")), "/")
 }
@@ -56,7 +57,7 @@ fn current_dir(default: &str) -> String {
         .to_owned()
 }
 
-#[cfg(any(unix))]
+#[cfg(unix)]
 pub fn current_location() -> String {
     current_dir("/")
 }
@@ -77,7 +78,7 @@ fn server_dir(default: &str) -> String {
         .to_owned()
 }
 
-#[cfg(any(unix))]
+#[cfg(unix)]
 pub fn server_location() -> String {
     server_dir("/")
 }
@@ -86,3 +87,52 @@ pub fn server_location() -> String {
 pub fn server_location() -> String {
     server_dir(r"C:\")
 }
+
+/// Instance folders of the MultiMC-based launchers that exist on this system.
+pub fn instance_locations() -> Vec<PathBuf> {
+    launcher_data_dirs()
+        .into_iter()
+        .map(|dir| dir.join("instances"))
+        .filter(|dir| dir.is_dir())
+        .collect()
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn launcher_data_dirs() -> Vec<PathBuf> {
+    let data = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| home_dir().map(|p| p.join(".local/share")));
+    let mut dirs = data
+        .map(|data| {
+            ["PrismLauncher", "PolyMC", "multimc"]
+                .map(|name| data.join(name))
+                .to_vec()
+        })
+        .unwrap_or_default();
+    if let Some(home) = home_dir() {
+        dirs.push(home.join(".var/app/org.prismlauncher.PrismLauncher/data/PrismLauncher"));
+    }
+    dirs
+}
+
+#[cfg(target_os = "windows")]
+fn launcher_data_dirs() -> Vec<PathBuf> {
+    std::env::var_os("APPDATA")
+        .map(|appdata| {
+            ["PrismLauncher", "PolyMC"]
+                .map(|name| PathBuf::from(&appdata).join(name))
+                .to_vec()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(target_os = "macos")]
+fn launcher_data_dirs() -> Vec<PathBuf> {
+    home_dir()
+        .map(|home| {
+            ["PrismLauncher", "PolyMC", "multimc"]
+                .map(|name| home.join("Library/Application Support").join(name))
+                .to_vec()
+        })
+        .unwrap_or_default()
+}